@@ -73,116 +73,419 @@ Update your analysis by handling situations where the Problem Dampener can remov
 from unsafe reports. How many reports are now safe?
 */
 
-
 /*
 
-rustc main.rs && ./main
+cargo run
+cargo run -- --report
 
 */
 
+use std::env;
+use std::fs;
+use std::io::{self};
 
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
+/// Which way the levels of a report are allowed to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Increasing,
+    Decreasing,
+    Either,
+}
 
-fn main_part_1() -> io::Result<()> {
-    let file = File::open(Path::new("input.txt"))?;
-    let reader = io::BufReader::new(file);
+/// Whether adjacent equal levels (a "flat" step) are tolerated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monotonicity {
+    Strict,
+    NonStrict,
+}
 
-    let mut counter = 0;
+/// The rules a report has to satisfy to be considered safe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafetyPolicy {
+    pub min_step: i32,
+    pub max_step: i32,
+    pub monotonicity: Monotonicity,
+    pub direction: Direction,
+}
 
-    for line in reader.lines() {
-        let line = line?;
-        let numbers: Vec<i32> = line
-            .split_whitespace()
-            .map(|s| s.parse().unwrap())
-            .collect();
+impl Default for SafetyPolicy {
+    /// The rules from the puzzle: all increasing or all decreasing, by 1 to 3 each step.
+    fn default() -> Self {
+        SafetyPolicy {
+            min_step: 1,
+            max_step: 3,
+            monotonicity: Monotonicity::Strict,
+            direction: Direction::Either,
+        }
+    }
+}
 
-        let mut inc = true;
-        let mut dec = true;
-        let mut safe = true;
+/// Why a report was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// The step changes direction relative to the earlier steps, e.g. `1 3 2`.
+    DirectionFlip,
+    /// The step goes against the direction required by the policy.
+    WrongDirection,
+    /// Neither an increase nor a decrease, e.g. `4 4`.
+    Flat,
+    StepTooSmall,
+    StepTooLarge,
+}
 
-        for i in 1..numbers.len() {
-            let diff = numbers[i] - numbers[i - 1];
-            if diff > 0 {
-                dec = false;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Safe,
+    /// `index` is the position of the first level of the offending `pair`.
+    Unsafe {
+        index: usize,
+        pair: (i32, i32),
+        reason: Reason,
+    },
+}
+
+impl SafetyPolicy {
+    pub fn verdict(&self, levels: &[i32]) -> Verdict {
+        // direction of the report so far, established by the first non-flat step
+        let mut established: Option<Direction> = None;
+
+        for (index, pair) in levels.windows(2).enumerate() {
+            let pair = (pair[0], pair[1]);
+            let diff = pair.1 - pair.0;
+            let unsafe_because = |reason| Verdict::Unsafe {
+                index,
+                pair,
+                reason,
+            };
+
+            if diff == 0 {
+                if self.monotonicity == Monotonicity::Strict {
+                    return unsafe_because(Reason::Flat);
+                }
+                continue;
             }
-            if diff < 0 {
-                inc = false;
+
+            let step = if diff > 0 {
+                Direction::Increasing
+            } else {
+                Direction::Decreasing
+            };
+
+            if self.direction != Direction::Either && self.direction != step {
+                return unsafe_because(Reason::WrongDirection);
             }
-            if diff.abs() > 3 || diff == 0 {
-                safe = false;
+            match established {
+                Some(direction) if direction != step => {
+                    return unsafe_because(Reason::DirectionFlip)
+                }
+                _ => established = Some(step),
             }
-        }
 
-        // ensure list is monotonic
-        if (inc || dec) && safe {
-            println!("{:?}", numbers);
-            counter += 1;
+            if diff.abs() < self.min_step {
+                return unsafe_because(Reason::StepTooSmall);
+            }
+            if diff.abs() > self.max_step {
+                return unsafe_because(Reason::StepTooLarge);
+            }
         }
 
+        Verdict::Safe
     }
 
-    println!("p1 {}", counter);
+    pub fn is_safe(&self, levels: &[i32]) -> bool {
+        self.verdict(levels) == Verdict::Safe
+    }
 
-    Ok(())
+    /// Index of the first level the Problem Dampener could remove to make an unsafe report safe.
+    pub fn dampener(&self, levels: &[i32]) -> Option<usize> {
+        if self.is_safe(levels) {
+            return None;
+        }
+        (0..levels.len()).find(|&i| {
+            let mut copy = levels.to_vec();
+            copy.remove(i);
+            self.is_safe(&copy)
+        })
+    }
 }
 
+/// Render one row per report: the levels, the verdict, and the dampener's choice (if any).
+pub fn report_table(reports: &[Vec<i32>], policy: &SafetyPolicy) -> String {
+    let rows: Vec<[String; 6]> = reports
+        .iter()
+        .map(|levels| {
+            let report = levels
+                .iter()
+                .map(|l| l.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let remove = match policy.dampener(levels) {
+                Some(i) => format!("{} ({})", i, levels[i]),
+                None => "-".to_string(),
+            };
+            match policy.verdict(levels) {
+                Verdict::Safe => [
+                    report,
+                    "safe".to_string(),
+                    "-".to_string(),
+                    "-".to_string(),
+                    "-".to_string(),
+                    remove,
+                ],
+                Verdict::Unsafe {
+                    index,
+                    pair,
+                    reason,
+                } => [
+                    report,
+                    "unsafe".to_string(),
+                    index.to_string(),
+                    format!("{} {}", pair.0, pair.1),
+                    format!("{:?}", reason),
+                    remove,
+                ],
+            }
+        })
+        .collect();
+
+    let header = ["report", "verdict", "index", "pair", "reason", "remove"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
 
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table.push_str(cells.join(" | ").trim_end());
+        table.push('\n');
+    }
+    table
+}
 
-fn is_safe(numbers: &Vec<i32>) -> bool {
-    let mut inc = true;
-    let mut dec = true;
-    let mut safe = true;
+fn parse(input: &str) -> Vec<Vec<i32>> {
+    input
+        .lines()
+        .map(|line| {
+            line.split_whitespace()
+                .map(|s| s.parse().unwrap())
+                .collect()
+        })
+        .collect()
+}
 
-    for i in 1..numbers.len() {
-        let diff = numbers[i] - numbers[i - 1];
-        if diff > 0 {
-            dec = false;
-        }
-        if diff < 0 {
-            inc = false;
-        }
-        if diff.abs() > 3 || diff == 0 {
-            safe = false;
-        }
-    }
+pub fn p1(input: &str) -> usize {
+    let policy = SafetyPolicy::default();
+    parse(input)
+        .iter()
+        .filter(|levels| policy.is_safe(levels))
+        .count()
+}
 
-    // ensure list is monotonic, and safe in elevation change
-    (inc || dec) && safe
+pub fn p2(input: &str) -> usize {
+    let policy = SafetyPolicy::default();
+    parse(input)
+        .iter()
+        .filter(|levels| policy.is_safe(levels) || policy.dampener(levels).is_some())
+        .count()
 }
 
+pub static INPUT: &str = "\
+7 6 4 2 1
+1 2 7 8 9
+9 7 6 2 1
+1 3 2 4 5
+8 6 4 4 1
+1 3 6 7 9";
+
 fn main() -> io::Result<()> {
-    let file = File::open(Path::new("input.txt"))?;
-    let reader = io::BufReader::new(file);
+    let input = fs::read_to_string("input.txt")?;
+    if env::args().any(|a| a == "--report") {
+        print!("{}", report_table(&parse(&input), &SafetyPolicy::default()));
+    }
+    println!("p1 {}", p1(&input));
+    println!("p2 {}", p2(&input));
+    Ok(())
+}
 
-    let mut counter = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_common::testing::XorShift;
 
-    'outer: for line in reader.lines() {
-        let line = line?;
-        let numbers: Vec<i32> = line
-            .split_whitespace()
-            .map(|s| s.parse().unwrap())
-            .collect();
+    #[test]
+    fn test_p1p2() {
+        assert_eq!(p1(INPUT), 2);
+        assert_eq!(p2(INPUT), 4);
+    }
 
-        println!("{:?}", numbers);
+    #[test]
+    fn test_verdicts() {
+        let policy = SafetyPolicy::default();
+        let verdicts: Vec<Verdict> = parse(INPUT).iter().map(|l| policy.verdict(l)).collect();
+        let unsafe_because = |index, pair, reason| Verdict::Unsafe {
+            index,
+            pair,
+            reason,
+        };
+        assert_eq!(
+            verdicts,
+            vec![
+                Verdict::Safe,
+                unsafe_because(1, (2, 7), Reason::StepTooLarge),
+                unsafe_because(2, (6, 2), Reason::StepTooLarge),
+                unsafe_because(1, (3, 2), Reason::DirectionFlip),
+                unsafe_because(2, (4, 4), Reason::Flat),
+                Verdict::Safe,
+            ]
+        );
+    }
 
-        // find all permutations of `numbers` if one element removed
-        for i in 0..numbers.len() {
-            let mut copy = numbers.clone();
-            copy.remove(i);
-            if is_safe(&copy) {
-                counter += 1;
-                continue 'outer;  // only count "safe" version once
+    #[test]
+    fn test_dampener() {
+        let policy = SafetyPolicy::default();
+        let removed: Vec<Option<usize>> = parse(INPUT).iter().map(|l| policy.dampener(l)).collect();
+        assert_eq!(removed, vec![None, None, None, Some(1), Some(2), None]);
+
+        // 1 3 5 9 11 can't be fixed by removing a single level
+        assert_eq!(policy.dampener(&[1, 3, 5, 9, 11]), None);
+        assert_eq!(policy.dampener(&[1, 3, 2, 5]), Some(1));
+    }
+
+    #[test]
+    fn test_policies() {
+        let unsafe_because = |index, pair, reason| Verdict::Unsafe {
+            index,
+            pair,
+            reason,
+        };
+        let increasing = SafetyPolicy {
+            direction: Direction::Increasing,
+            ..SafetyPolicy::default()
+        };
+        assert_eq!(increasing.verdict(&[1, 2, 4]), Verdict::Safe);
+        assert_eq!(
+            increasing.verdict(&[4, 2, 1]),
+            unsafe_because(0, (4, 2), Reason::WrongDirection)
+        );
+
+        let relaxed = SafetyPolicy {
+            min_step: 2,
+            monotonicity: Monotonicity::NonStrict,
+            ..SafetyPolicy::default()
+        };
+        assert_eq!(relaxed.verdict(&[8, 8, 6, 6, 3]), Verdict::Safe);
+        assert_eq!(
+            relaxed.verdict(&[8, 8, 7]),
+            unsafe_because(1, (8, 7), Reason::StepTooSmall)
+        );
+        assert_eq!(SafetyPolicy::default().dampener(&[8, 8, 7]), Some(0));
+    }
+
+    #[test]
+    fn test_report_table() {
+        let table = report_table(&parse(INPUT), &SafetyPolicy::default());
+        assert_eq!(
+            table,
+            "\
+report    | verdict | index | pair | reason        | remove
+7 6 4 2 1 | safe    | -     | -    | -             | -
+1 2 7 8 9 | unsafe  | 1     | 2 7  | StepTooLarge  | -
+9 7 6 2 1 | unsafe  | 2     | 6 2  | StepTooLarge  | -
+1 3 2 4 5 | unsafe  | 1     | 3 2  | DirectionFlip | 1 (3)
+8 6 4 4 1 | unsafe  | 2     | 4 4  | Flat          | 2 (4)
+1 3 6 7 9 | safe    | -     | -    | -             | -
+"
+        );
+    }
+
+    fn random_report(rng: &mut XorShift) -> Vec<i32> {
+        let len = 1 + rng.below(8);
+        let mut level = rng.below(100) as i32;
+        (0..len)
+            .map(|_| {
+                // mostly small steps, so that a fair share of reports are (nearly) safe
+                level += rng.below(9) as i32 - 4;
+                level
+            })
+            .collect()
+    }
+
+    /// The original part 1 check, written out without `SafetyPolicy`.
+    fn baseline_safe(numbers: &[i32]) -> bool {
+        let (mut inc, mut dec, mut safe) = (true, true, true);
+        for i in 1..numbers.len() {
+            let diff = numbers[i] - numbers[i - 1];
+            if diff > 0 {
+                dec = false;
+            }
+            if diff < 0 {
+                inc = false;
+            }
+            if diff.abs() > 3 || diff == 0 {
+                safe = false;
             }
         }
+        (inc || dec) && safe
+    }
 
-        if is_safe(&numbers) {
-            counter += 1;
-        }
+    /// The original part 2: safe as it is, or after removing any one level.
+    fn baseline_dampened(numbers: &[i32]) -> bool {
+        baseline_safe(numbers)
+            || (0..numbers.len()).any(|i| {
+                let mut copy = numbers.to_vec();
+                copy.remove(i);
+                baseline_safe(&copy)
+            })
     }
 
-    println!("p1 {}", counter);
+    #[test]
+    fn prop_matches_brute_force() {
+        let policy = SafetyPolicy::default();
+        let mut rng = XorShift::new(0x2024_1202);
+        for _ in 0..10_000 {
+            let report = random_report(&mut rng);
+            let line = report
+                .iter()
+                .map(|l| l.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            assert_eq!(p1(&line), baseline_safe(&report) as usize, "{:?}", report);
+            assert_eq!(
+                p2(&line),
+                baseline_dampened(&report) as usize,
+                "{:?}",
+                report
+            );
+
+            // whatever the dampener removes really does leave a safe report
+            if let Some(i) = policy.dampener(&report) {
+                let mut dampened = report.clone();
+                dampened.remove(i);
+                assert!(baseline_safe(&dampened), "{:?} without {}", report, i);
+            }
+        }
+    }
 
-    Ok(())
+    #[test]
+    fn prop_p1_safe_implies_p2_safe() {
+        let policy = SafetyPolicy::default();
+        let mut rng = XorShift::new(0xdead_beef);
+        for _ in 0..5_000 {
+            let report = random_report(&mut rng);
+            let line = report
+                .iter()
+                .map(|l| l.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            assert!(!policy.is_safe(&report) || p2(&line) == 1, "{}", line);
+            assert!(p1(&line) <= p2(&line), "{}", line);
+        }
+    }
 }
@@ -0,0 +1,153 @@
+//! Showing which parts of the memory are real instructions, and what each of them contributed.
+
+use crate::machine::{Machine, Overflow, Registry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Executed while instructions were enabled.
+    Enabled,
+    /// Executed while instructions were disabled, e.g. a `mul` after `don't()`.
+    Disabled,
+    /// Changes whether instructions are enabled, like `do()` and `don't()`.
+    Toggle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub offset: usize,
+    pub len: usize,
+    pub kind: Kind,
+    /// Change to the accumulator.
+    pub contribution: i64,
+}
+
+/// Run `input` on a fresh machine, recording every instruction that `registry` recognised.
+pub fn trace(registry: &Registry, input: &[u8]) -> Result<Vec<Step>, Overflow> {
+    let mut machine = Machine::default();
+    let mut steps = Vec::new();
+
+    for token in registry.lexer().tokenize(input) {
+        let definition = match registry.lookup(&token) {
+            Some(definition) => definition,
+            None => continue,
+        };
+
+        // an instruction is a toggle if it can change the flag from either state
+        let toggles = [true, false].into_iter().any(|enabled| {
+            let mut probe = Machine {
+                enabled,
+                ..machine.clone()
+            };
+            (definition.exec)(&mut probe, &token.args);
+            probe.enabled != enabled
+        });
+        let kind = match (toggles, machine.enabled) {
+            (true, _) => Kind::Toggle,
+            (false, true) => Kind::Enabled,
+            (false, false) => Kind::Disabled,
+        };
+
+        let before = machine.acc;
+        machine.execute(registry, &token)?;
+        steps.push(Step {
+            offset: token.offset,
+            len: token.len,
+            kind,
+            contribution: machine.acc - before,
+        });
+    }
+
+    Ok(steps)
+}
+
+/// Split `input` into plain and highlighted segments, wrapping each instruction with `markup`.
+fn highlight(
+    input: &[u8],
+    steps: &[Step],
+    markup: impl Fn(&str, Option<Kind>) -> String,
+) -> String {
+    let mut out = String::new();
+    let mut pos = 0;
+    for step in steps {
+        out.push_str(&markup(
+            &String::from_utf8_lossy(&input[pos..step.offset]),
+            None,
+        ));
+        out.push_str(&markup(
+            &String::from_utf8_lossy(&input[step.offset..step.offset + step.len]),
+            Some(step.kind),
+        ));
+        pos = step.offset + step.len;
+    }
+    out.push_str(&markup(&String::from_utf8_lossy(&input[pos..]), None));
+    out
+}
+
+/// The memory with instructions coloured for a terminal.
+pub fn ansi(input: &[u8], steps: &[Step]) -> String {
+    highlight(input, steps, |text, kind| {
+        let colour = match kind {
+            None => return text.to_string(),
+            Some(Kind::Enabled) => "1;32",
+            Some(Kind::Disabled) => "2;31",
+            Some(Kind::Toggle) => "1;33",
+        };
+        format!("\x1b[{}m{}\x1b[0m", colour, text)
+    })
+}
+
+/// A standalone HTML page with the same highlighting as [`ansi`].
+pub fn html(input: &[u8], steps: &[Step]) -> String {
+    let body = highlight(input, steps, |text, kind| {
+        let text = text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        match kind {
+            None => text,
+            Some(kind) => format!(
+                "<span class=\"{}\">{}</span>",
+                format!("{:?}", kind).to_lowercase(),
+                text
+            ),
+        }
+    });
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<style>
+pre {{ white-space: pre-wrap; word-break: break-all; }}
+.enabled {{ color: #00cc00; font-weight: bold; }}
+.disabled {{ color: #cc0000; opacity: 0.6; }}
+.toggle {{ color: #cccc00; font-weight: bold; }}
+</style>
+</head>
+<body>
+<pre>{}</pre>
+</body>
+</html>
+",
+        body
+    )
+}
+
+/// One line per instruction: offset, text, contribution and kind.
+pub fn listing(input: &[u8], steps: &[Step]) -> String {
+    let mut out = format!(
+        "{:>8}  {:<16} {:>12}  {}\n",
+        "offset", "instruction", "contribution", "kind"
+    );
+    for step in steps {
+        let text = String::from_utf8_lossy(&input[step.offset..step.offset + step.len]);
+        out.push_str(&format!(
+            "{:>8}  {:<16} {:>12}  {:?}\n",
+            step.offset, text, step.contribution, step.kind
+        ));
+    }
+    let total: i64 = steps.iter().map(|s| s.contribution).sum();
+    out.push_str(&format!("{:>8}  {:<16} {:>12}\n", "", "total", total));
+    out
+}
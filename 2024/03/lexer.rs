@@ -0,0 +1,169 @@
+//! Tokenizer for corrupted memory.
+//!
+//! Every well-formed call `name(args)` is turned into a [`Token`], where `args` is a possibly
+//! empty comma-separated list of numbers of at most `max_digits` digits. The name is taken from
+//! the run of identifier characters directly in front of the parenthesis; when that run ends in a
+//! known instruction name taking that many arguments (`xmul(2,4)`, `undo()`) only the longest such
+//! suffix is the instruction, mirroring how the puzzle highlights `mul(2,4)` inside `xmul(2,4)`.
+//! Otherwise the whole run is the name.
+//!
+//! The lexer knows nothing about what instructions do, see [`Registry`](crate::machine::Registry).
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// Byte offset of the first character of the instruction name.
+    pub offset: usize,
+    /// Length in bytes, up to and including the closing parenthesis.
+    pub len: usize,
+    pub name: String,
+    pub args: Vec<i64>,
+}
+
+pub struct Lexer {
+    /// Known instruction names with their arities, longest first.
+    names: Vec<(String, usize)>,
+    max_digits: usize,
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b == b'\''
+}
+
+impl Lexer {
+    pub fn new<S: AsRef<str>>(names: &[(S, usize)], max_digits: usize) -> Self {
+        // arguments are parsed into an i64
+        assert!(
+            (1..=18).contains(&max_digits),
+            "max_digits must be in 1..=18"
+        );
+
+        let mut names: Vec<(String, usize)> = names
+            .iter()
+            .map(|(name, arity)| (name.as_ref().to_string(), *arity))
+            .collect();
+        names.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+
+        Lexer { names, max_digits }
+    }
+
+    /// Memory isn't necessarily valid UTF-8, so this works on raw bytes.
+    pub fn tokenize<'a>(&'a self, input: &'a [u8]) -> Tokens<'a> {
+        Tokens {
+            lexer: self,
+            input,
+            pos: 0,
+            state: State::Ident { start: 0 },
+            args: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Inside a (possibly empty) run of identifier characters that began at `start`.
+    Ident { start: usize },
+    /// Inside the argument list opened at `open`, reading an argument with `digits` digits so far.
+    Args {
+        start: usize,
+        open: usize,
+        digits: usize,
+        value: i64,
+    },
+}
+
+/// Iterator over the tokens in a byte slice; a single forward pass without backtracking.
+pub struct Tokens<'a> {
+    lexer: &'a Lexer,
+    input: &'a [u8],
+    pos: usize,
+    state: State,
+    args: Vec<i64>,
+}
+
+impl Tokens<'_> {
+    fn emit(&mut self, start: usize, open: usize) -> Token {
+        // only ASCII identifier characters can be in the name
+        let ident = std::str::from_utf8(&self.input[start..open]).unwrap();
+        let name = self
+            .lexer
+            .names
+            .iter()
+            .find(|(known, arity)| *arity == self.args.len() && ident.ends_with(known.as_str()))
+            .map_or(ident, |(known, _)| known.as_str());
+        let offset = open - name.len();
+
+        Token {
+            offset,
+            len: self.pos + 1 - offset,
+            name: name.to_string(),
+            args: std::mem::take(&mut self.args),
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while let Some(&b) = self.input.get(self.pos) {
+            match self.state {
+                State::Ident { start } => {
+                    if b == b'(' {
+                        self.args.clear();
+                        self.state = State::Args {
+                            start,
+                            open: self.pos,
+                            digits: 0,
+                            value: 0,
+                        };
+                    } else if !is_ident(b) {
+                        self.state = State::Ident {
+                            start: self.pos + 1,
+                        };
+                    }
+                }
+                State::Args {
+                    start,
+                    open,
+                    digits,
+                    value,
+                } => match b {
+                    b'0'..=b'9' if digits < self.lexer.max_digits => {
+                        self.state = State::Args {
+                            start,
+                            open,
+                            digits: digits + 1,
+                            value: value * 10 + (b - b'0') as i64,
+                        };
+                    }
+                    b',' if digits > 0 => {
+                        self.args.push(value);
+                        self.state = State::Args {
+                            start,
+                            open,
+                            digits: 0,
+                            value: 0,
+                        };
+                    }
+                    b')' if digits > 0 || self.args.is_empty() => {
+                        if digits > 0 {
+                            self.args.push(value);
+                        }
+                        let token = self.emit(start, open);
+                        self.pos += 1;
+                        self.state = State::Ident { start: self.pos };
+                        return Some(token);
+                    }
+                    _ => {
+                        // not a call after all; this byte may still start the next one, so
+                        // look at it again
+                        self.state = State::Ident { start: self.pos };
+                        continue;
+                    }
+                },
+            }
+            self.pos += 1;
+        }
+        None
+    }
+}
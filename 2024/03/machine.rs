@@ -0,0 +1,175 @@
+//! Interpreter for the token stream produced by the [`Lexer`](crate::lexer::Lexer).
+//!
+//! What each instruction does lives in a [`Registry`], so that puzzle variants only need to
+//! register their instructions; tokens the registry doesn't recognise are corrupted memory.
+
+use crate::lexer::{Lexer, Token};
+use crate::stream::Scanner;
+use std::fmt;
+use std::io::{self, Read};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Machine {
+    /// Whether `mul` instructions currently contribute to the accumulator.
+    pub enabled: bool,
+    pub acc: i64,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Machine {
+            enabled: true,
+            acc: 0,
+        }
+    }
+}
+
+/// Effect of an instruction on the machine, given its arguments. Returns `None` if the
+/// arithmetic overflowed, use checked operations and `?`.
+pub type Semantics = fn(&mut Machine, &[i64]) -> Option<()>;
+
+/// An instruction whose arithmetic overflowed the accumulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overflow {
+    pub offset: usize,
+    pub name: String,
+    pub args: Vec<i64>,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|a| a.to_string()).collect();
+        write!(
+            f,
+            "arithmetic overflow in {}({}) at offset {}",
+            self.name,
+            args.join(","),
+            self.offset
+        )
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub arity: usize,
+    pub exec: Semantics,
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    definitions: Vec<Definition>,
+    /// Maximum number of digits in an argument, `mul(X,Y)` takes 1-3 digit numbers.
+    pub max_digits: usize,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry {
+            definitions: Vec::new(),
+            max_digits: 3,
+        }
+    }
+}
+
+impl Registry {
+    /// `mul(X,Y)` only.
+    pub fn part_1() -> Self {
+        Registry::default().register("mul", 2, |m, args| {
+            if m.enabled {
+                m.acc = m.acc.checked_add(args[0].checked_mul(args[1])?)?;
+            }
+            Some(())
+        })
+    }
+
+    /// `mul(X,Y)` along with the `do()` and `don't()` conditionals.
+    pub fn part_2() -> Self {
+        Registry::part_1()
+            .register("do", 0, |m, _| {
+                m.enabled = true;
+                Some(())
+            })
+            .register("don't", 0, |m, _| {
+                m.enabled = false;
+                Some(())
+            })
+    }
+
+    /// Add an instruction; a name may be registered once per arity.
+    pub fn register(mut self, name: &str, arity: usize, exec: Semantics) -> Self {
+        self.definitions
+            .retain(|d| !(d.name == name && d.arity == arity));
+        self.definitions.push(Definition {
+            name: name.to_string(),
+            arity,
+            exec,
+        });
+        self
+    }
+
+    pub fn lexer(&self) -> Lexer {
+        let names: Vec<(&str, usize)> = self
+            .definitions
+            .iter()
+            .map(|d| (d.name.as_str(), d.arity))
+            .collect();
+        Lexer::new(&names, self.max_digits)
+    }
+
+    /// Upper bound on the length of a call to any registered instruction.
+    pub fn max_call_len(&self) -> usize {
+        self.definitions
+            .iter()
+            .map(|d| d.name.len() + 2 + d.arity * (self.max_digits + 1))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn lookup(&self, token: &Token) -> Option<&Definition> {
+        self.definitions
+            .iter()
+            .find(|d| d.name == token.name && d.arity == token.args.len())
+    }
+}
+
+impl Machine {
+    /// Execute `token` if `registry` knows it, returning whether it did.
+    pub fn execute(&mut self, registry: &Registry, token: &Token) -> Result<bool, Overflow> {
+        match registry.lookup(token) {
+            Some(definition) => match (definition.exec)(self, &token.args) {
+                Some(()) => Ok(true),
+                None => Err(Overflow {
+                    offset: token.offset,
+                    name: token.name.clone(),
+                    args: token.args.clone(),
+                }),
+            },
+            None => Ok(false),
+        }
+    }
+
+    pub fn run(&mut self, registry: &Registry, input: &[u8]) -> Result<i64, Overflow> {
+        for token in registry.lexer().tokenize(input) {
+            self.execute(registry, &token)?;
+        }
+        Ok(self.acc)
+    }
+
+    /// Like [`Machine::run`], but streaming the memory from `reader` in `chunk_size` pieces. An
+    /// overflow is reported as an [`io::ErrorKind::InvalidData`] error.
+    pub fn run_reader<R: Read>(
+        &mut self,
+        registry: &Registry,
+        reader: R,
+        chunk_size: usize,
+    ) -> io::Result<i64> {
+        for token in Scanner::new(reader, registry, chunk_size) {
+            self.execute(registry, &token?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        Ok(self.acc)
+    }
+}
@@ -65,78 +65,424 @@ multiplications?
 /*
 
 cargo run
+cargo run -- --explain
+cargo run -- --explain --html explain.html
+
+cargo test --release -- --ignored --nocapture bench
 
 */
 
-use regex::Regex;
-use std::fs;
+mod explain;
+mod lexer;
+mod machine;
+mod stream;
+
+use machine::{Machine, Overflow, Registry};
+use std::env;
+use std::fs::{self, File};
 use std::io::{self};
+use stream::CHUNK_SIZE;
 
-pub fn tally_muls(input: &String) -> i32 {
-    let re = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
-    let mut counter = 0;
-    for (_, [a, b]) in re.captures_iter(&input).map(|c| c.extract()) {
-        let ai: i32 = a.parse().unwrap();
-        let bi: i32 = b.parse().unwrap();
-        counter += ai * bi;
+pub fn tally_muls(input: &str) -> Result<i64, Overflow> {
+    Machine::default().run(&Registry::part_1(), input.as_bytes())
+}
+
+pub fn tally_muls_pt2(input: &str) -> Result<i64, Overflow> {
+    Machine::default().run(&Registry::part_2(), input.as_bytes())
+}
+
+/// Print the memory with its instructions highlighted, or write it to an HTML file.
+fn explain(html: Option<&str>) -> io::Result<()> {
+    let input = fs::read("input.txt")?;
+    let steps = explain::trace(&Registry::part_2(), &input)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    match html {
+        Some(path) => fs::write(path, explain::html(&input, &steps))?,
+        None => println!("{}", explain::ansi(&input, &steps)),
     }
-    counter
+    print!("{}", explain::listing(&input, &steps));
+
+    Ok(())
 }
 
-pub fn tally_muls_pt2(input: &String) -> i32 {
-    let re_line = Regex::new(r"do\(\)|don't\(\)|mul\((\d{1,3}),(\d{1,3})\)").unwrap();
-    let re_mul = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
-
-    let mut counter = 0;
-    let mut enabled = true;
-    for caps in re_line.captures_iter(&input) {
-        let instruction = caps.get(0).unwrap().as_str();
-
-        match instruction {
-            "do()" => enabled = true,
-            "don't()" => enabled = false,
-            _ => {
-                if enabled {
-                    let mul = caps.get(0).unwrap().as_str();
-                    let caps = re_mul.captures(mul).unwrap();
-                    let a: i32 = caps[1].parse().unwrap();
-                    let b: i32 = caps[2].parse().unwrap();
-                    counter += a * b;
-                }
-            }
-        }
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--explain") {
+        let html = args
+            .iter()
+            .position(|a| a == "--html")
+            .map(|i| args.get(i + 1).map_or("explain.html", |p| p.as_str()));
+        return explain(html);
     }
-    counter
+
+    // stream the memory, rather than loading all of it
+    let p1 =
+        Machine::default().run_reader(&Registry::part_1(), File::open("input.txt")?, CHUNK_SIZE)?;
+    let p2 =
+        Machine::default().run_reader(&Registry::part_2(), File::open("input.txt")?, CHUNK_SIZE)?;
+
+    println!("p1 {}", p1);
+    println!("p2 {}", p2);
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::explain::{self, Kind, Step};
+    use super::lexer::Token;
+    use super::machine::{Machine, Registry};
+    use super::stream::Scanner;
+    use advent_of_code_common::testing::{time, XorShift};
+    use regex::Regex;
+    use std::io::{self, Read};
+
+    static INPUT: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
     #[test]
     fn test_pt1() {
-        let input =
-            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))".to_string();
-        assert_eq!(super::tally_muls(&input), 161);
+        assert_eq!(super::tally_muls(INPUT), Ok(161));
     }
 
     #[test]
     fn test_pt2() {
-        let input =
-            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))".to_string();
-        assert_eq!(super::tally_muls_pt2(&input), 48);
+        assert_eq!(super::tally_muls_pt2(INPUT), Ok(48));
     }
-}
 
-fn main() -> io::Result<()> {
-    let mut counter_pt1 = 0;
-    let mut counter_pt2 = 0;
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = Registry::part_2()
+            .lexer()
+            .tokenize(INPUT.as_bytes())
+            .collect();
+        let expected = vec![
+            (1, "mul(2,4)", "mul", vec![2, 4]),
+            (20, "don't()", "don't", vec![]),
+            (28, "mul(5,5)", "mul", vec![5, 5]),
+            (48, "mul(11,8)", "mul", vec![11, 8]),
+            (59, "do()", "do", vec![]),
+            (64, "mul(8,5)", "mul", vec![8, 5]),
+        ];
+        assert_eq!(tokens.len(), expected.len());
+        for (token, (offset, text, name, args)) in tokens.iter().zip(expected) {
+            assert_eq!(token.offset, offset);
+            assert_eq!(&INPUT[token.offset..token.offset + token.len], text);
+            assert_eq!(token.name, name);
+            assert_eq!(token.args, args);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_unknown_calls() {
+        let registry = Registry::part_2();
+        let tokens: Vec<(String, Vec<i64>, bool)> = registry
+            .lexer()
+            .tokenize(b"what()mul(5)from(12,345)mul(1234,5)do(1)")
+            .map(|t| {
+                let known = registry.lookup(&t).is_some();
+                (t.name, t.args, known)
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("what".to_string(), vec![], false),
+                ("mul".to_string(), vec![5], false),
+                ("from".to_string(), vec![12, 345], false),
+                ("do".to_string(), vec![1], false),
+            ]
+        );
+    }
 
-    let line = fs::read_to_string("input.txt")?;
+    #[test]
+    fn test_custom_instructions() {
+        let registry = Registry::part_2()
+            .register("add", 2, |m, args| {
+                if m.enabled {
+                    m.acc = m.acc.checked_add(args[0].checked_add(args[1])?)?;
+                }
+                Some(())
+            })
+            .register("neg", 1, |m, args| {
+                m.acc = m.acc.checked_sub(args[0])?;
+                Some(())
+            })
+            .register("reset", 0, |m, _| {
+                m.acc = 0;
+                Some(())
+            });
+
+        let input = "mul(2,3)xadd(1,2)don't()add(5,5)do()neg(4)";
+        assert_eq!(
+            Machine::default().run(&registry, input.as_bytes()),
+            Ok(2 * 3 + 1 + 2 - 4)
+        );
+
+        let input = "mul(2,3)reset()mul(4,5)";
+        assert_eq!(Machine::default().run(&registry, input.as_bytes()), Ok(20));
+
+        // a new instruction ending in an existing name only takes calls with its own arity
+        let registry = Registry::part_2().register("undo", 1, |m, _| {
+            m.enabled = false;
+            Some(())
+        });
+        let input = "don't()undo()mul(2,3)";
+        assert_eq!(Machine::default().run(&registry, input.as_bytes()), Ok(6));
+        let input = "undo(1)mul(2,3)";
+        assert_eq!(Machine::default().run(&registry, input.as_bytes()), Ok(0));
+    }
 
-    counter_pt1 += tally_muls(&line);
-    counter_pt2 += tally_muls_pt2(&line);
+    #[test]
+    fn test_digit_limit() {
+        let input = "mul(1234,2)mul(12,3)";
+        assert_eq!(
+            Machine::default().run(&Registry::part_1(), input.as_bytes()),
+            Ok(36)
+        );
+        let mut registry = Registry::part_1();
+        registry.max_digits = 4;
+        assert_eq!(
+            Machine::default().run(&registry, input.as_bytes()),
+            Ok(2468 + 36)
+        );
+    }
 
-    println!("p1 {}", counter_pt1);
-    println!("p2 {}", counter_pt2);
+    #[test]
+    fn test_stream_matches_in_memory() {
+        let registry = Registry::part_2();
+        let expected: Vec<Token> = registry.lexer().tokenize(INPUT.as_bytes()).collect();
+        for chunk_size in 1..=INPUT.len() + 1 {
+            let tokens: Vec<Token> = Scanner::new(INPUT.as_bytes(), &registry, chunk_size)
+                .collect::<io::Result<_>>()
+                .unwrap();
+            assert_eq!(tokens, expected, "chunk_size {}", chunk_size);
+
+            let mut machine = Machine::default();
+            let acc = machine.run_reader(&registry, INPUT.as_bytes(), chunk_size);
+            assert_eq!(acc.unwrap(), 48, "chunk_size {}", chunk_size);
+            // the last toggle in the memory was a `do()`
+            assert!(machine.enabled);
+        }
+    }
 
-    Ok(())
+    /// Hands out at most `max` bytes per read, like a pipe or socket would.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        max: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.max.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_stream_short_reads() {
+        let input = INPUT.repeat(100);
+        for max in [1, 2, 3, 7, 64] {
+            let reader = Trickle {
+                data: input.as_bytes(),
+                max,
+            };
+            let acc = Machine::default().run_reader(&Registry::part_2(), reader, 16);
+            assert_eq!(acc.unwrap(), super::tally_muls_pt2(&input).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut registry = Registry::part_1();
+        registry.max_digits = 18;
+
+        // 999_999_999_999_999_999 * 9 no longer fits an i64
+        let input = "mul(999999999999999999,1)mul(999999999999999999,9)";
+        let err = Machine::default()
+            .run(&registry, input.as_bytes())
+            .unwrap_err();
+        assert_eq!(err.offset, 25);
+        assert_eq!(err.name, "mul");
+        assert_eq!(err.args, vec![999_999_999_999_999_999, 9]);
+
+        // far more than an i32 could hold
+        let input = "mul(999999999,999999999)".repeat(9);
+        assert_eq!(
+            Machine::default().run(&registry, input.as_bytes()),
+            Ok(9 * 999_999_999 * 999_999_999)
+        );
+
+        let err = Machine::default()
+            .run_reader(
+                &registry,
+                "mul(999999999,999999999)".repeat(10).as_bytes(),
+                7,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // The regex implementation the state machine replaced, kept as a reference.
+
+    fn regex_tally_muls(input: &str) -> i32 {
+        let re = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
+        let mut counter = 0;
+        for (_, [a, b]) in re.captures_iter(input).map(|c| c.extract()) {
+            let ai: i32 = a.parse().unwrap();
+            let bi: i32 = b.parse().unwrap();
+            counter += ai * bi;
+        }
+        counter
+    }
+
+    fn regex_tally_muls_pt2(input: &str) -> i32 {
+        let re_line = Regex::new(r"do\(\)|don't\(\)|mul\((\d{1,3}),(\d{1,3})\)").unwrap();
+        let re_mul = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
+
+        let mut counter = 0;
+        let mut enabled = true;
+        for caps in re_line.captures_iter(input) {
+            match caps.get(0).unwrap().as_str() {
+                "do()" => enabled = true,
+                "don't()" => enabled = false,
+                mul => {
+                    if enabled {
+                        let caps = re_mul.captures(mul).unwrap();
+                        let a: i32 = caps[1].parse().unwrap();
+                        let b: i32 = caps[2].parse().unwrap();
+                        counter += a * b;
+                    }
+                }
+            }
+        }
+        counter
+    }
+
+    /// Corrupted memory built from fragments that are easily confused with instructions.
+    fn corrupted_memory(seed: u64, fragments: usize) -> String {
+        const PIECES: &[&str] = &[
+            "mul",
+            "mul(",
+            "(",
+            ")",
+            ",",
+            "do",
+            "don't",
+            "()",
+            "x",
+            "_",
+            "'",
+            "1",
+            "23",
+            "456",
+            "7890",
+            "mul(2,4)",
+            "mul(123,45)",
+            "do()",
+            "don't()",
+            "undo()",
+            "!",
+            " ",
+            "[",
+            "]",
+        ];
+        let mut rng = XorShift::new(seed);
+        (0..fragments)
+            .map(|_| PIECES[rng.below(PIECES.len() as u64) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_regex() {
+        for seed in 1..200 {
+            let input = corrupted_memory(seed, 500);
+            assert_eq!(
+                super::tally_muls(&input),
+                Ok(regex_tally_muls(&input) as i64),
+                "{}",
+                input
+            );
+            assert_eq!(
+                super::tally_muls_pt2(&input),
+                Ok(regex_tally_muls_pt2(&input) as i64),
+                "{}",
+                input
+            );
+        }
+    }
+
+    /// cargo test --release -- --ignored --nocapture bench
+    #[test]
+    #[ignore]
+    fn bench_regex_vs_state_machine() {
+        let input = corrupted_memory(2024, 4_000_000);
+        println!("{} bytes of memory", input.len());
+
+        for _ in 0..3 {
+            let a = time("regex p1", || regex_tally_muls(&input) as i64);
+            let b = time("state machine p1", || super::tally_muls(&input).unwrap());
+            assert_eq!(a, b);
+            let a = time("regex p2", || regex_tally_muls_pt2(&input) as i64);
+            let b = time("state machine p2", || {
+                super::tally_muls_pt2(&input).unwrap()
+            });
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let steps = explain::trace(&Registry::part_2(), INPUT.as_bytes()).unwrap();
+        let step = |offset, len, kind, contribution| Step {
+            offset,
+            len,
+            kind,
+            contribution,
+        };
+        assert_eq!(
+            steps,
+            vec![
+                step(1, 8, Kind::Enabled, 8),
+                step(20, 7, Kind::Toggle, 0),
+                step(28, 8, Kind::Disabled, 0),
+                step(48, 9, Kind::Disabled, 0),
+                step(59, 4, Kind::Toggle, 0),
+                step(64, 8, Kind::Enabled, 40),
+            ]
+        );
+
+        // the four highlighted sections from part one
+        let steps = explain::trace(&Registry::part_1(), INPUT.as_bytes()).unwrap();
+        assert_eq!(steps.len(), 4);
+        assert!(steps.iter().all(|s| s.kind == Kind::Enabled));
+    }
+
+    #[test]
+    fn test_highlighting() {
+        let input = b"xmul(2,4)don't()mul(3,3)<do()>";
+        let steps = explain::trace(&Registry::part_2(), input).unwrap();
+
+        assert_eq!(
+            explain::ansi(input, &steps),
+            "x\x1b[1;32mmul(2,4)\x1b[0m\x1b[1;33mdon't()\x1b[0m\x1b[2;31mmul(3,3)\x1b[0m<\x1b[1;33mdo()\x1b[0m>"
+        );
+        assert!(explain::html(input, &steps).contains(
+            "<pre>x<span class=\"enabled\">mul(2,4)</span><span class=\"toggle\">don't()</span>\
+             <span class=\"disabled\">mul(3,3)</span>&lt;<span class=\"toggle\">do()</span>&gt;</pre>"
+        ));
+        assert_eq!(
+            explain::listing(input, &steps),
+            "  offset  instruction      contribution  kind
+       1  mul(2,4)                    8  Enabled
+       9  don't()                     0  Toggle
+      16  mul(3,3)                    0  Disabled
+      25  do()                        0  Toggle
+          total                       8
+"
+        );
+    }
 }
@@ -0,0 +1,131 @@
+//! Tokenizing memory straight from an [`io::Read`], a chunk at a time.
+//!
+//! After each chunk is tokenized, everything up to the last byte that cannot be part of an
+//! unfinished call is dropped. What is carried over into the next chunk is capped at the longest
+//! call any registered instruction can produce, so memory use is bounded by the chunk size no
+//! matter how large the input is. As a consequence, the names of unknown calls preceded by a very
+//! long run of identifier characters may be truncated if they straddle a chunk boundary.
+
+use crate::lexer::{Lexer, Token};
+use crate::machine::Registry;
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct Scanner<R: Read> {
+    reader: R,
+    lexer: Lexer,
+    chunk_size: usize,
+    max_carry: usize,
+    buf: Vec<u8>,
+    /// Absolute byte offset of `buf[0]` in the input.
+    base: usize,
+    pending: VecDeque<Token>,
+    done: bool,
+}
+
+impl<R: Read> Scanner<R> {
+    pub fn new(reader: R, registry: &Registry, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        Scanner {
+            reader,
+            lexer: registry.lexer(),
+            chunk_size,
+            max_carry: registry.max_call_len(),
+            buf: Vec::with_capacity(chunk_size + registry.max_call_len()),
+            base: 0,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Read and tokenize the next chunk, returning false once the reader is exhausted.
+    fn fill(&mut self) -> io::Result<bool> {
+        let start = self.buf.len();
+        self.buf.resize(start + self.chunk_size, 0);
+        let n = loop {
+            match self.reader.read(&mut self.buf[start..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buf.truncate(start);
+                    return Err(e);
+                }
+            }
+        };
+        self.buf.truncate(start + n);
+
+        let mut end = 0;
+        for mut token in self.lexer.tokenize(&self.buf) {
+            end = token.offset + token.len;
+            token.offset += self.base;
+            self.pending.push_back(token);
+        }
+
+        // keep the trailing bytes that may still grow into a call; a complete call ends in `)`,
+        // so this never overlaps a token emitted above
+        let carry = if n == 0 {
+            self.buf.len()
+        } else {
+            let trailing = self
+                .buf
+                .iter()
+                .rposition(|&b| !(b.is_ascii_alphanumeric() || b"_'(,".contains(&b)))
+                .map_or(0, |i| i + 1);
+            end.max(trailing)
+                .max(self.buf.len().saturating_sub(self.max_carry))
+        };
+        self.buf.drain(..carry);
+        self.base += carry;
+
+        Ok(n > 0)
+    }
+}
+
+impl<R: Read> Iterator for Scanner<R> {
+    type Item = io::Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(Ok(token));
+            }
+            if self.done {
+                return None;
+            }
+            match self.fill() {
+                Ok(more) => self.done = !more,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_buffer() {
+        // a long run of identifier characters must not be carried over in its entirety
+        let mut input = vec![b'a'; 1 << 20];
+        input.extend_from_slice(b"mul(2,3)");
+
+        let registry = Registry::part_1();
+        let mut scanner = Scanner::new(&input[..], &registry, 4096);
+        let mut tokens = Vec::new();
+        while let Some(token) = scanner.next() {
+            tokens.push(token.unwrap());
+            assert!(scanner.buf.capacity() <= 4096 + registry.max_call_len());
+        }
+        assert!(scanner.buf.capacity() <= 4096 + registry.max_call_len());
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].offset, 1 << 20);
+        assert_eq!(tokens[0].args, vec![2, 3]);
+    }
+}
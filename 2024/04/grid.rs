@@ -0,0 +1,88 @@
+//! A rectangular grid of letters, addressed by `(row, col)`.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    cells: Vec<Vec<char>>,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Grid {
+    pub fn parse(input: &str) -> Self {
+        let cells: Vec<Vec<char>> = input.lines().map(|row| row.chars().collect()).collect();
+        let height = cells.len();
+        let width = cells.iter().map(|row| row.len()).max().unwrap_or(0);
+        Grid {
+            cells,
+            height,
+            width,
+        }
+    }
+
+    /// The letter at `pos`, or `None` outside of the grid (or past the end of a short row).
+    pub fn get(&self, pos: (isize, isize)) -> Option<char> {
+        if pos.0 < 0 || pos.1 < 0 {
+            return None;
+        }
+        self.cells
+            .get(pos.0 as usize)
+            .and_then(|row| row.get(pos.1 as usize))
+            .copied()
+    }
+
+    /// Move from `pos` by `delta`, optionally wrapping around the edges of the grid.
+    pub fn step(&self, pos: (isize, isize), delta: (isize, isize), wrap: bool) -> (isize, isize) {
+        let next = (pos.0 + delta.0, pos.1 + delta.1);
+        if wrap {
+            (
+                next.0.rem_euclid(self.height as isize),
+                next.1.rem_euclid(self.width as isize),
+            )
+        } else {
+            next
+        }
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.height).flat_map(move |row| (0..self.cells[row].len()).map(move |col| (row, col)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::N,
+        Direction::NE,
+        Direction::E,
+        Direction::SE,
+        Direction::S,
+        Direction::SW,
+        Direction::W,
+        Direction::NW,
+    ];
+
+    /// `(row, col)` offset of a single step.
+    pub fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::N => (-1, 0),
+            Direction::NE => (-1, 1),
+            Direction::E => (0, 1),
+            Direction::SE => (1, 1),
+            Direction::S => (1, 0),
+            Direction::SW => (1, -1),
+            Direction::W => (0, -1),
+            Direction::NW => (-1, -1),
+        }
+    }
+}
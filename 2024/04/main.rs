@@ -82,108 +82,305 @@ In this example, an X-MAS appears 9 times.
 /*
 
 cargo run
+cargo run -- --render
 
 */
 
+mod grid;
+mod pattern;
+mod render;
+mod search;
+
+use grid::Grid;
+use pattern::{Matcher, Pattern};
+use search::WordSearch;
+use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::io::{self};
 
+fn xmas() -> WordSearch {
+    WordSearch::new(&["XMAS"])
+}
+
+fn x_mas() -> Matcher {
+    Matcher::new(Pattern::parse("M.S/.A./M.S"), true)
+}
+
 pub fn p1(input: &str) -> i32 {
-    let grid: Vec<Vec<char>> = input.lines().map(|row| row.chars().collect()).collect();
+    xmas().find(&Grid::parse(input)).len() as i32
+}
+
+/// How many XMASes use each cell.
+pub fn p1_usage(grid: &Grid) -> HashMap<(usize, usize), usize> {
+    let search = xmas();
+    render::usage(search.find(grid).iter().map(|m| search.cells(grid, m)))
+}
+
+pub fn p2(input: &str) -> i32 {
+    x_mas().find(&Grid::parse(input)).len() as i32
+}
+
+/// How many X-MASes use each cell.
+pub fn p2_usage(grid: &Grid) -> HashMap<(usize, usize), usize> {
+    render::usage(x_mas().find(grid).into_iter().map(|m| m.cells))
+}
+
+fn main() -> io::Result<()> {
+    let input = fs::read_to_string("input.txt")?;
+    if env::args().any(|a| a == "--render") {
+        let grid = Grid::parse(&input);
+        for (part, usage) in [(1, p1_usage(&grid)), (2, p2_usage(&grid))] {
+            println!("p{}\n{}", part, render::masked(&grid, &usage));
+            println!("p{} usage\n{}", part, render::overlay(&grid, &usage));
+        }
+    }
+    println!("p1 {}", p1(&input));
+    println!("p2 {}", p2(&input));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::grid::Direction;
+    use super::search::Match;
+    use super::*;
+    use advent_of_code_common::testing::XorShift;
+    static INPUT: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\nXXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX";
 
-    let mut counter = 0;
+    #[test]
+    fn test_p1() {
+        assert_eq!(p1(INPUT), 18);
+    }
 
-    for j in 0..grid.len() {
-        for i in 0..grid[j].len() {
-            // vertical
-            if j + 3 < grid.len() {
-                let entry = (grid[j][i], grid[j + 1][i], grid[j + 2][i], grid[j + 3][i]);
-                if entry == ('X', 'M', 'A', 'S') || entry == ('S', 'A', 'M', 'X') {
+    // The original scan of every cell for XMAS or SAMX in four orientations, kept as a reference.
+    fn tuple_p1(input: &str) -> i32 {
+        let grid: Vec<Vec<char>> = input.lines().map(|row| row.chars().collect()).collect();
+        let found = |entry| entry == ('X', 'M', 'A', 'S') || entry == ('S', 'A', 'M', 'X');
+
+        let mut counter = 0;
+        for j in 0..grid.len() {
+            for i in 0..grid[j].len() {
+                // vertical
+                if j + 3 < grid.len()
+                    && found((grid[j][i], grid[j + 1][i], grid[j + 2][i], grid[j + 3][i]))
+                {
                     counter += 1;
                 }
-            }
-
-            // horizontal
-            if i + 3 < grid[j].len() {
-                let entry = (grid[j][i], grid[j][i + 1], grid[j][i + 2], grid[j][i + 3]);
-                if entry == ('X', 'M', 'A', 'S') || entry == ('S', 'A', 'M', 'X') {
+                // horizontal
+                if i + 3 < grid[j].len()
+                    && found((grid[j][i], grid[j][i + 1], grid[j][i + 2], grid[j][i + 3]))
+                {
                     counter += 1;
                 }
-            }
-
-            // diagonal \
-            if j + 3 < grid.len() && i + 3 < grid[j].len() {
-                let entry = (
-                    grid[j][i],
-                    grid[j + 1][i + 1],
-                    grid[j + 2][i + 2],
-                    grid[j + 3][i + 3],
-                );
-                if entry == ('X', 'M', 'A', 'S') || entry == ('S', 'A', 'M', 'X') {
+                // diagonal \
+                if j + 3 < grid.len()
+                    && i + 3 < grid[j].len()
+                    && found((
+                        grid[j][i],
+                        grid[j + 1][i + 1],
+                        grid[j + 2][i + 2],
+                        grid[j + 3][i + 3],
+                    ))
+                {
                     counter += 1;
                 }
-            }
-
-            // diagonal /
-            if j + 3 < grid.len() && i as isize - 3 >= 0 {
-                let entry = (
-                    grid[j][i],
-                    grid[j + 1][i - 1],
-                    grid[j + 2][i - 2],
-                    grid[j + 3][i - 3],
-                );
-                if entry == ('X', 'M', 'A', 'S') || entry == ('S', 'A', 'M', 'X') {
+                // diagonal /
+                if j + 3 < grid.len()
+                    && i >= 3
+                    && found((
+                        grid[j][i],
+                        grid[j + 1][i - 1],
+                        grid[j + 2][i - 2],
+                        grid[j + 3][i - 3],
+                    ))
+                {
                     counter += 1;
                 }
             }
         }
+        counter
     }
 
-    counter
-}
+    #[test]
+    fn test_matches_tuple_scan() {
+        let mut rng = XorShift::new(0x0404);
+        for _ in 0..200 {
+            let (height, width) = (1 + rng.below(12) as usize, 1 + rng.below(12) as usize);
+            let input: String = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| b"XMASXMAS."[rng.below(9) as usize] as char)
+                        .collect::<String>()
+                        + "\n"
+                })
+                .collect();
+            assert_eq!(p1(&input), tuple_p1(&input), "\n{}", input);
+        }
+    }
 
-pub fn p2(input: &str) -> i32 {
-    let grid: Vec<Vec<char>> = input.lines().map(|row| row.chars().collect()).collect();
+    #[test]
+    fn test_p2() {
+        assert_eq!(p2(INPUT), 9);
+    }
 
-    let mut counter = 0;
+    #[test]
+    fn test_word_search() {
+        let grid = Grid::parse("..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X....");
+        let matches = WordSearch::new(&["XMAS"]).find(&grid);
+        let m = |start, direction| Match {
+            start,
+            direction,
+            word: "XMAS".to_string(),
+        };
+        assert_eq!(
+            matches,
+            vec![
+                m((0, 2), Direction::SE),
+                m((1, 4), Direction::W),
+                m((3, 0), Direction::E),
+                m((4, 1), Direction::N),
+            ]
+        );
+    }
 
-    for j in 0..grid.len() {
-        for i in 0..grid[j].len() {
-            if j + 2 < grid.len() && i + 2 < grid[j].len() {
-                let e1 = (grid[j][i], grid[j + 1][i + 1], grid[j + 2][i + 2]);
-                let e2 = (grid[j][i + 2], grid[j + 1][i + 1], grid[j + 2][i]);
+    #[test]
+    fn test_many_words() {
+        let grid = Grid::parse(INPUT);
+        let matches = WordSearch::new(&["XMAS", "MAS", "SAMX", "XMAS"]).find(&grid);
+        let count = |word: &str| matches.iter().filter(|m| m.word == word).count();
+        // every XMAS read backwards is a SAMX, and contains a MAS
+        assert_eq!(count("XMAS"), 18);
+        assert_eq!(count("SAMX"), 18);
+        assert_eq!(count("MAS"), 38);
+        assert_eq!(matches.len(), 36 + count("MAS"));
+    }
 
-                if (e1 == ('M', 'A', 'S') || e1 == ('S', 'A', 'M'))
-                    && (e2 == ('M', 'A', 'S') || e2 == ('S', 'A', 'M'))
-                {
-                    counter += 1;
-                }
-            }
-        }
+    #[test]
+    fn test_wraparound() {
+        let grid = Grid::parse("ASXM\n....");
+        assert!(WordSearch::new(&["XMAS"]).find(&grid).is_empty());
+
+        let mut search = WordSearch::new(&["XMAS"]);
+        search.wrap = true;
+        let matches = search.find(&grid);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].start, (0, 2));
+        assert_eq!(matches[0].direction, Direction::E);
     }
 
-    counter
-}
+    #[test]
+    fn test_symmetries() {
+        let x_mas = Pattern::parse("M.S/.A./M.S");
+        let variants = x_mas.symmetries();
+        assert_eq!(variants.len(), 4);
+        for template in ["M.M/.A./S.S", "S.M/.A./S.M", "S.S/.A./M.M"] {
+            assert!(variants.contains(&Pattern::parse(template)), "{}", template);
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    static INPUT: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\nXXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX";
+        // fully symmetric shapes collapse to a single variant
+        assert_eq!(Pattern::parse("A.A/.A./A.A").symmetries().len(), 1);
+        // a straight word has its four rotations, each of which reflects onto another
+        assert_eq!(Pattern::parse("XMAS").symmetries().len(), 4);
+        assert_eq!(Pattern::parse("XM/A.").symmetries().len(), 8);
+    }
 
     #[test]
-    fn test_p1() {
-        assert_eq!(p1(&INPUT), 18);
+    fn test_pattern_matcher() {
+        let grid = Grid::parse(INPUT);
+
+        // without rotations only the one orientation is found
+        let upright = Matcher::new(Pattern::parse("M.S/.A./M.S"), false).find(&grid);
+        assert_eq!(upright.len(), 2);
+        assert!(upright.iter().all(|m| m.variant == 0));
+
+        // a straight word through the same engine finds the horizontal and vertical XMASes
+        let words = WordSearch::new(&["XMAS"]).find(&grid);
+        let straight = words
+            .iter()
+            .filter(|m| {
+                matches!(
+                    m.direction,
+                    Direction::N | Direction::E | Direction::S | Direction::W
+                )
+            })
+            .count();
+        let xmas = Matcher::new(Pattern::parse("XMAS"), true).find(&grid);
+        assert_eq!(xmas.len(), straight);
+
+        // symmetric matches of a symmetric shape are only counted once
+        let grid = Grid::parse("A.A\n.A.\nA.A");
+        assert_eq!(
+            Matcher::new(Pattern::parse("A.A/.A./A.A"), true)
+                .find(&grid)
+                .len(),
+            1
+        );
+        assert_eq!(
+            Matcher::new(Pattern::parse("AA"), true)
+                .find(&Grid::parse("AA"))
+                .len(),
+            1
+        );
     }
 
     #[test]
-    fn test_p2() {
-        assert_eq!(p2(&INPUT), 9);
+    fn test_masked() {
+        let grid = Grid::parse(INPUT);
+        assert_eq!(
+            render::masked(&grid, &p1_usage(&grid)),
+            "\
+....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+"
+        );
+        assert_eq!(
+            render::masked(&grid, &p2_usage(&grid)),
+            "\
+.M.S......
+..A..MSMS.
+.M.S.MAA..
+..A.ASMSM.
+.M.S.M....
+..........
+S.S.S.S.S.
+.A.A.A.A..
+M.M.M.M.M.
+..........
+"
+        );
     }
-}
 
-fn main() -> io::Result<()> {
-    let input = fs::read_to_string("input.txt")?;
-    println!("p1 {}", p1(&input));
-    println!("p2 {}", p2(&input));
-    Ok(())
+    #[test]
+    fn test_overlay() {
+        let grid = Grid::parse("..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X....");
+        assert_eq!(
+            render::overlay(&grid, &p1_usage(&grid)),
+            "\
+..1...
+.2121.
+.1..1.
+1211.1
+.1....
+"
+        );
+
+        let grid = Grid::parse(INPUT);
+        let usage = p2_usage(&grid);
+        let overlay = render::overlay(&grid, &usage);
+        // every X-MAS has one A of its own, and four more letters
+        assert_eq!(
+            overlay.matches(|c: char| c.is_ascii_digit()).count(),
+            usage.len()
+        );
+        assert_eq!(usage.values().sum::<usize>(), 9 * 5);
+    }
 }
@@ -0,0 +1,140 @@
+//! Matching small 2D templates against a [`Grid`].
+//!
+//! A template is written row by row, separated by `/` (or newlines), with `.` standing for any
+//! letter: the X-MAS shape is `M.S/.A./M.S`. A [`Matcher`] can also try every rotation and
+//! reflection of the template; transformed templates that coincide are only tried once, and a
+//! set of cells is only reported once even if several variants match it.
+
+use crate::grid::Grid;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    /// `(row, col, letter)` of every non-wildcard cell, sorted.
+    cells: Vec<(usize, usize, char)>,
+    height: usize,
+    width: usize,
+}
+
+impl Pattern {
+    pub fn parse(template: &str) -> Self {
+        let rows: Vec<&str> = template.split(['/', '\n']).collect();
+        let cells = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c != '.')
+                    .map(move |(col, c)| (row, col, c))
+            })
+            .collect();
+        Pattern::new(
+            cells,
+            rows.len(),
+            rows.iter().map(|r| r.chars().count()).max().unwrap_or(0),
+        )
+    }
+
+    fn new(mut cells: Vec<(usize, usize, char)>, height: usize, width: usize) -> Self {
+        cells.sort();
+        Pattern {
+            cells,
+            height,
+            width,
+        }
+    }
+
+    /// A quarter turn clockwise.
+    pub fn rotate(&self) -> Self {
+        let cells = self
+            .cells
+            .iter()
+            .map(|&(row, col, c)| (col, self.height - 1 - row, c))
+            .collect();
+        Pattern::new(cells, self.width, self.height)
+    }
+
+    /// Mirrored left to right.
+    pub fn reflect(&self) -> Self {
+        let cells = self
+            .cells
+            .iter()
+            .map(|&(row, col, c)| (row, self.width - 1 - col, c))
+            .collect();
+        Pattern::new(cells, self.height, self.width)
+    }
+
+    /// The distinct templates among all rotations and reflections, starting with `self`.
+    pub fn symmetries(&self) -> Vec<Pattern> {
+        let mut variants: Vec<Pattern> = Vec::new();
+        for mut variant in [self.clone(), self.reflect()] {
+            for _ in 0..4 {
+                if !variants.contains(&variant) {
+                    variants.push(variant.clone());
+                }
+                variant = variant.rotate();
+            }
+        }
+        variants
+    }
+
+    fn matches_at(&self, grid: &Grid, origin: (usize, usize)) -> bool {
+        self.cells.iter().all(|&(row, col, c)| {
+            grid.get(((origin.0 + row) as isize, (origin.1 + col) as isize)) == Some(c)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+    /// `(row, col)` of the top left corner of the matching variant.
+    pub origin: (usize, usize),
+    /// Index into [`Matcher::variants`].
+    pub variant: usize,
+    /// `(row, col)` of every non-wildcard cell, sorted.
+    pub cells: Vec<(usize, usize)>,
+}
+
+#[derive(Debug)]
+pub struct Matcher {
+    pub variants: Vec<Pattern>,
+}
+
+impl Matcher {
+    /// Match `pattern` as written, or under all of its rotations and reflections.
+    pub fn new(pattern: Pattern, symmetric: bool) -> Self {
+        let variants = if symmetric {
+            pattern.symmetries()
+        } else {
+            vec![pattern]
+        };
+        Matcher { variants }
+    }
+
+    /// Every match, ordered by origin, with each set of cells reported once.
+    pub fn find(&self, grid: &Grid) -> Vec<PatternMatch> {
+        let mut seen: HashSet<Vec<(usize, usize)>> = HashSet::new();
+        let mut matches = Vec::new();
+        for origin in grid.positions() {
+            for (variant, pattern) in self.variants.iter().enumerate() {
+                if !pattern.matches_at(grid, origin) {
+                    continue;
+                }
+                let cells: Vec<(usize, usize)> = pattern
+                    .cells
+                    .iter()
+                    .map(|&(row, col, _)| (origin.0 + row, origin.1 + col))
+                    .collect();
+                if seen.insert(cells.clone()) {
+                    matches.push(PatternMatch {
+                        origin,
+                        variant,
+                        cells,
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
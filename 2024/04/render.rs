@@ -0,0 +1,48 @@
+//! Rendering the grid the way the puzzle explains its answers.
+
+use crate::grid::Grid;
+use std::collections::HashMap;
+
+/// Count how many matches use each cell.
+pub fn usage<I>(matches: I) -> HashMap<(usize, usize), usize>
+where
+    I: IntoIterator<Item = Vec<(usize, usize)>>,
+{
+    let mut counts = HashMap::new();
+    for cells in matches {
+        for cell in cells {
+            *counts.entry(cell).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+fn render(grid: &Grid, cell: impl Fn((usize, usize), char) -> char) -> String {
+    let mut out = String::new();
+    for row in 0..grid.height {
+        for col in 0..grid.width {
+            if let Some(c) = grid.get((row as isize, col as isize)) {
+                out.push(cell((row, col), c));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The grid with every letter not used by any match replaced by `.`.
+pub fn masked(grid: &Grid, usage: &HashMap<(usize, usize), usize>) -> String {
+    render(
+        grid,
+        |pos, c| if usage.contains_key(&pos) { c } else { '.' },
+    )
+}
+
+/// The number of matches using each cell, `.` for none and `+` for more than nine.
+pub fn overlay(grid: &Grid, usage: &HashMap<(usize, usize), usize>) -> String {
+    render(grid, |pos, _| match usage.get(&pos) {
+        None => '.',
+        Some(&n) if n > 9 => '+',
+        Some(&n) => char::from_digit(n as u32, 10).unwrap(),
+    })
+}
@@ -0,0 +1,107 @@
+//! Word search over a [`Grid`] in all eight directions.
+//!
+//! The words are stored in a trie, so from each cell and direction the grid is walked only as
+//! long as it spells the prefix of some word. The cost of a search grows with the length of the
+//! longest word rather than the number of words.
+
+use crate::grid::{Direction, Grid};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Match {
+    /// `(row, col)` of the first letter.
+    pub start: (usize, usize),
+    pub direction: Direction,
+    pub word: String,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: HashMap<char, usize>,
+    /// Index into `WordSearch::words` if a word ends here.
+    word: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct WordSearch {
+    nodes: Vec<Node>,
+    words: Vec<String>,
+    depth: usize,
+    /// Whether words may run off one edge of the grid and continue on the opposite edge.
+    pub wrap: bool,
+}
+
+impl WordSearch {
+    pub fn new<S: AsRef<str>>(words: &[S]) -> Self {
+        let mut search = WordSearch {
+            nodes: vec![Node::default()],
+            words: Vec::new(),
+            depth: 0,
+            wrap: false,
+        };
+
+        for word in words {
+            let word = word.as_ref();
+            let mut node = 0;
+            for c in word.chars() {
+                node = match search.nodes[node].children.get(&c) {
+                    Some(&child) => child,
+                    None => {
+                        search.nodes.push(Node::default());
+                        let child = search.nodes.len() - 1;
+                        search.nodes[node].children.insert(c, child);
+                        child
+                    }
+                };
+            }
+            if search.nodes[node].word.is_none() && !word.is_empty() {
+                search.nodes[node].word = Some(search.words.len());
+                search.words.push(word.to_string());
+                search.depth = search.depth.max(word.chars().count());
+            }
+        }
+
+        search
+    }
+
+    /// `(row, col)` of every letter of `m`, in reading order of the word.
+    pub fn cells(&self, grid: &Grid, m: &Match) -> Vec<(usize, usize)> {
+        let mut pos = (m.start.0 as isize, m.start.1 as isize);
+        let mut cells = Vec::new();
+        for _ in m.word.chars() {
+            cells.push((pos.0 as usize, pos.1 as usize));
+            pos = grid.step(pos, m.direction.delta(), self.wrap);
+        }
+        cells
+    }
+
+    /// Every occurrence of every word, ordered by start position, direction and word.
+    pub fn find(&self, grid: &Grid) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for start in grid.positions() {
+            for direction in Direction::ALL {
+                let mut pos = (start.0 as isize, start.1 as isize);
+                let mut node = 0;
+                for _ in 0..self.depth {
+                    let child = grid
+                        .get(pos)
+                        .and_then(|c| self.nodes[node].children.get(&c));
+                    node = match child {
+                        Some(&child) => child,
+                        None => break,
+                    };
+                    if let Some(word) = self.nodes[node].word {
+                        matches.push(Match {
+                            start,
+                            direction,
+                            word: self.words[word].clone(),
+                        });
+                    }
+                    pos = grid.step(pos, direction.delta(), self.wrap);
+                }
+            }
+        }
+        matches.sort();
+        matches
+    }
+}
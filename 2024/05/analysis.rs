@@ -0,0 +1,101 @@
+//! Consistency analysis of the rule set, and pruning of rules that don't affect any answer.
+
+use crate::rules::Rules;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub pages: usize,
+    pub rules: usize,
+    /// Pairs `(X, Y)`, `X != Y`, where `X` must come before `Y` in the transitive closure.
+    pub implied: usize,
+    /// Rules that follow from the others, regardless of which pages are in an update.
+    pub redundant: Vec<(i32, i32)>,
+    /// Rules `X|Y` where the other rules also imply `Y|X`.
+    pub contradictions: Vec<(i32, i32)>,
+}
+
+pub fn analyse(rules: &Rules) -> Analysis {
+    let pages = rules.pages();
+    let all = rules.rules();
+
+    let implied = pages
+        .iter()
+        .map(|&x| rules.reachable(x, |_| true, None).len())
+        .sum::<usize>()
+        // a page on a cycle reaches itself, which isn't an ordering
+        - pages
+            .iter()
+            .filter(|&&x| rules.reachable(x, |_| true, None).contains(&x))
+            .count();
+
+    let redundant = all
+        .iter()
+        .copied()
+        .filter(|&(x, y)| rules.reachable(x, |_| true, Some((x, y))).contains(&y))
+        .collect();
+
+    let contradictions = all
+        .iter()
+        .copied()
+        .filter(|&(x, y)| rules.reachable(y, |_| true, None).contains(&x))
+        .collect();
+
+    Analysis {
+        pages: pages.len(),
+        rules: all.len(),
+        implied,
+        redundant,
+        contradictions,
+    }
+}
+
+impl Analysis {
+    pub fn to_text(&self) -> String {
+        let list = |rules: &[(i32, i32)]| {
+            rules
+                .iter()
+                .map(|(x, y)| format!("  {}|{}\n", x, y))
+                .collect::<String>()
+        };
+        format!(
+            "pages {}\nrules {}\nimplied orderings {}\nredundant rules {}\n{}contradictions {}\n{}",
+            self.pages,
+            self.rules,
+            self.implied,
+            self.redundant.len(),
+            list(&self.redundant),
+            self.contradictions.len(),
+            list(&self.contradictions),
+        )
+    }
+}
+
+/// The smallest subset of the rules that gives the same answers for `updates`.
+///
+/// Rules only apply between pages of the same update, so a rule can be dropped only if, within
+/// every update containing both of its pages, it is implied by a chain of rules through other
+/// pages of that update. Updates whose rules form a cycle keep all of their rules, so that the
+/// cycle is still reported.
+pub fn minimise(rules: &Rules, updates: &[Vec<i32>]) -> Vec<(i32, i32)> {
+    let mut kept: HashSet<(i32, i32)> = HashSet::new();
+    for update in updates {
+        let pages: HashSet<i32> = update.iter().copied().collect();
+        let acyclic = rules.topological(update).is_ok();
+        for &x in update {
+            for y in rules.successors(x).filter(|y| pages.contains(y)) {
+                let implied = acyclic
+                    && rules
+                        .reachable(x, |p| pages.contains(&p), Some((x, y)))
+                        .contains(&y);
+                if !implied {
+                    kept.insert((x, y));
+                }
+            }
+        }
+    }
+
+    let mut kept: Vec<(i32, i32)> = kept.into_iter().collect();
+    kept.sort();
+    kept
+}
@@ -101,42 +101,100 @@ Determine which updates are already in the correct order. What do you get if you
 
 */
 
+mod analysis;
+mod report;
+mod rules;
+
+use advent_of_code_common::sections::{self, ParseError};
+use report::Report;
+use rules::{Cycle, Rules, Totality};
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
 use std::fs;
 use std::io::{self};
 
-fn _parse(input: &str, delimiter: char) -> Vec<Vec<i32>> {
-    input
-        .lines()
-        .map(|line| {
-            line.split(delimiter)
-                .filter_map(|s| s.trim().parse::<i32>().ok())
-                .collect()
-        })
-        .collect()
+/// Reading the input or putting pages in order failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    Cycle(Cycle),
 }
 
-pub fn p1(input: &str) -> i32 {
-    let (rules, updates) = input.split_once("\n\n").unwrap();
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
 
-    let mut counter = 0;
-    for update in _parse(updates, ',') {
-        let mut valid = true;
-        for rule in _parse(rules, '|') {
-            if update.contains(&rule[0]) && update.contains(&rule[1]) {
-                let x1 = update.iter().position(|&x| x == rule[0]).unwrap();
-                let x2 = update.iter().position(|&x| x == rule[1]).unwrap();
-                if x1 > x2 {
-                    valid = false;
-                }
-            }
-        }
+impl From<Cycle> for Error {
+    fn from(e: Cycle) -> Self {
+        Error::Cycle(e)
+    }
+}
 
-        if valid {
-            counter += &update[update.len() / 2];
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(e) => e.fmt(f),
+            Error::Cycle(e) => e.fmt(f),
         }
     }
+}
+
+impl std::error::Error for Error {}
+
+fn parse_page(page: &str, line: &str) -> Result<i32, String> {
+    page.trim()
+        .parse()
+        .map_err(|_| format!("invalid page number {:?} in {:?}", page, line))
+}
+
+fn parse_rule(line: &str) -> Result<(i32, i32), String> {
+    let (x, y) = line
+        .split_once('|')
+        .ok_or_else(|| format!("expected a rule X|Y, got {:?}", line))?;
+    Ok((parse_page(x, line)?, parse_page(y, line)?))
+}
+
+fn parse_update(line: &str) -> Result<Vec<i32>, String> {
+    let pages = line
+        .split(',')
+        .map(|page| parse_page(page, line))
+        .collect::<Result<Vec<i32>, String>>()?;
+    if pages.len() % 2 == 0 {
+        return Err(format!(
+            "update {:?} has an even number of pages, so no middle page",
+            line
+        ));
+    }
+    let mut seen = HashSet::new();
+    if let Some(page) = pages.iter().find(|&&p| !seen.insert(p)) {
+        return Err(format!(
+            "update {:?} lists page {} more than once",
+            line, page
+        ));
+    }
+    Ok(pages)
+}
+
+/// The rules and the updates, or the first malformed line.
+pub fn parse(input: &str) -> Result<(Rules, Vec<Vec<i32>>), ParseError> {
+    let [rules, updates] = sections::split(input, ["rules", "updates"])?;
+    Ok((
+        Rules::new(rules.parse(parse_rule)?),
+        updates.parse(parse_update)?,
+    ))
+}
+
+pub fn p1(input: &str) -> Result<i32, Error> {
+    let (rules, updates) = parse(input)?;
 
-    counter
+    Ok(updates
+        .iter()
+        .filter(|update| rules.is_ordered(update))
+        .map(|update| update[update.len() / 2])
+        .sum())
 }
 
 /*
@@ -160,41 +218,52 @@ Find the updates which are not in the correct order. What do you get if you add
 numbers after correctly ordering just those updates?
 */
 
-pub fn p2(input: &str) -> i32 {
-    let (rules, updates) = input.split_once("\n\n").unwrap();
+pub fn p2(input: &str) -> Result<i32, Error> {
+    let (rules, updates) = parse(input)?;
 
     let mut counter = 0;
-
-    for (ix, mut update) in _parse(updates, ',').into_iter().enumerate() {
-        let mut fixed = false;
-        loop {
-            let mut updated_for_all_rules = false;
-            for rule in _parse(rules, '|') {
-                if update.contains(&rule[0]) && update.contains(&rule[1]) {
-                    let x1 = update.iter().position(|&x| x == rule[0]).unwrap();
-                    let x2 = update.iter().position(|&x| x == rule[1]).unwrap();
-                    if x1 > x2 {
-                        // swap records, and indicate that it has been updated
-                        update.swap(x1, x2);
-                        updated_for_all_rules = true;
-                        fixed = true;
-                    }
-                }
-            }
-
-            // loop until no updates were required
-            if !updated_for_all_rules {
-                break;
-            }
+    for update in updates {
+        if !rules.is_ordered(&update) {
+            let fixed = rules.repair(&update)?;
+            counter += fixed[fixed.len() / 2];
         }
+    }
+    Ok(counter)
+}
 
-        if fixed {
-            println!("{:?}", (ix, fixed));
-            counter += update[update.len() / 2];
-        }
+/// The input with only the rules that matter to its updates, see [`analysis::minimise`], or with
+/// just its first rule if none do.
+pub fn minimised(input: &str) -> Result<String, ParseError> {
+    let (rules, updates) = parse(input)?;
+    let mut kept = analysis::minimise(&rules, &updates);
+    if kept.is_empty() {
+        // the rules section can't be left empty, so keep one rule no update is affected by
+        kept = rules.rules()[..1].to_vec();
     }
 
-    counter
+    let rules: Vec<String> = kept.iter().map(|(x, y)| format!("{}|{}", x, y)).collect();
+    let updates: Vec<String> = updates
+        .iter()
+        .map(|update| {
+            update
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect();
+    Ok(format!("{}\n\n{}\n", rules.join("\n"), updates.join("\n")))
+}
+
+/// A [`Report`] for every update, ordered or not.
+pub fn reports(input: &str) -> Result<Vec<Report>, Error> {
+    let (rules, updates) = parse(input)?;
+
+    Ok(updates
+        .iter()
+        .enumerate()
+        .map(|(index, update)| Report::new(&rules, index, update))
+        .collect::<Result<_, _>>()?)
 }
 
 pub static INPUT: &str = "\
@@ -227,20 +296,378 @@ pub static INPUT: &str = "\
 61,13,29
 97,13,75,29,47";
 
+fn invalid(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn main() -> io::Result<()> {
+    let input = fs::read_to_string("input.txt")?;
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--report") {
+        let reports = reports(&input).map_err(invalid)?;
+        if args.iter().any(|a| a == "--json") {
+            print!("{}", report::to_json(&reports));
+        } else {
+            reports.iter().for_each(|r| print!("{}", r.to_text()));
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|a| a == "--analyse") {
+        let (rules, _) = parse(&input).map_err(invalid)?;
+        print!("{}", analysis::analyse(&rules).to_text());
+        return Ok(());
+    }
+    if args.iter().any(|a| a == "--minimise") {
+        print!("{}", minimised(&input).map_err(invalid)?);
+        return Ok(());
+    }
+
+    println!("p1 {}", p1(&input).map_err(invalid)?);
+    println!("p2 {}", p2(&input).map_err(invalid)?);
+
+    let (rules, _) = parse(&input).map_err(invalid)?;
+    match rules.totality() {
+        Totality::Total(_) => println!("the rules are a total order"),
+        Totality::Partial(a, b) => println!("the rules don't order {} and {}", a, b),
+        Totality::Cyclic(cycle) => println!("the rules aren't an order, {}", cycle),
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_common::testing::{time, XorShift};
+
+    fn _parse(input: &str, delimiter: char) -> Vec<Vec<i32>> {
+        input
+            .lines()
+            .map(|line| {
+                line.split(delimiter)
+                    .filter_map(|s| s.trim().parse::<i32>().ok())
+                    .collect()
+            })
+            .collect()
+    }
 
     #[test]
     fn test_p1() {
-        assert_eq!(p1(&INPUT), 143);
-        assert_eq!(p2(&INPUT), 123);
+        assert_eq!(p1(INPUT), Ok(143));
+        assert_eq!(p2(INPUT), Ok(123));
     }
-}
 
-fn main() -> io::Result<()> {
-    let input = fs::read_to_string("input.txt")?;
-    println!("p1 {}", p1(&input));
-    println!("p2 {}", p2(&input));
-    Ok(())
+    #[test]
+    fn test_sort() {
+        let (rules, _) = parse(INPUT).unwrap();
+        for (update, fixed) in [
+            (vec![75, 97, 47, 61, 53], vec![97, 75, 47, 61, 53]),
+            (vec![61, 13, 29], vec![61, 29, 13]),
+            (vec![97, 13, 75, 29, 47], vec![97, 75, 47, 29, 13]),
+        ] {
+            assert!(!rules.is_ordered(&update));
+            assert!(rules.totally_orders(&update));
+            let mut update = update;
+            rules.sort(&mut update);
+            assert_eq!(update, fixed);
+            assert!(rules.is_ordered(&update));
+        }
+    }
+
+    // The implementation that re-parsed the rules per update and repaired by swapping, kept as a
+    // reference for the benchmark.
+
+    fn swap_p1(input: &str) -> i32 {
+        let (rules, updates) = input.split_once("\n\n").unwrap();
+        let mut counter = 0;
+        for update in _parse(updates, ',') {
+            let mut valid = true;
+            for rule in _parse(rules, '|') {
+                if update.contains(&rule[0]) && update.contains(&rule[1]) {
+                    let x1 = update.iter().position(|&x| x == rule[0]).unwrap();
+                    let x2 = update.iter().position(|&x| x == rule[1]).unwrap();
+                    if x1 > x2 {
+                        valid = false;
+                    }
+                }
+            }
+            if valid {
+                counter += &update[update.len() / 2];
+            }
+        }
+        counter
+    }
+
+    fn swap_p2(input: &str) -> i32 {
+        let (rules, updates) = input.split_once("\n\n").unwrap();
+        let mut counter = 0;
+        for mut update in _parse(updates, ',') {
+            let mut fixed = false;
+            loop {
+                let mut updated_for_all_rules = false;
+                for rule in _parse(rules, '|') {
+                    if update.contains(&rule[0]) && update.contains(&rule[1]) {
+                        let x1 = update.iter().position(|&x| x == rule[0]).unwrap();
+                        let x2 = update.iter().position(|&x| x == rule[1]).unwrap();
+                        if x1 > x2 {
+                            update.swap(x1, x2);
+                            updated_for_all_rules = true;
+                            fixed = true;
+                        }
+                    }
+                }
+                if !updated_for_all_rules {
+                    break;
+                }
+            }
+            if fixed {
+                counter += update[update.len() / 2];
+            }
+        }
+        counter
+    }
+
+    /// Rules totally ordering `pages` pages, and `updates` shuffled updates of 23 pages each.
+    fn large_input(pages: i32, updates: usize) -> String {
+        let mut rng = XorShift::new(0x0505);
+
+        let mut input = String::new();
+        for x in 0..pages {
+            for y in x + 1..pages {
+                input.push_str(&format!("{}|{}\n", x, y));
+            }
+        }
+        input.push('\n');
+
+        let lines: Vec<String> = (0..updates)
+            .map(|_| {
+                let mut update: Vec<i32> = Vec::new();
+                while update.len() < 23 {
+                    let page = rng.below(pages as u64) as i32;
+                    if !update.contains(&page) {
+                        update.push(page);
+                    }
+                }
+                // leave some of the updates in order
+                if rng.below(4) == 0 {
+                    update.sort();
+                }
+                update
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect();
+        input + &lines.join("\n")
+    }
+
+    #[test]
+    fn test_matches_swapping() {
+        let input = large_input(40, 20);
+        assert_eq!(p1(&input), Ok(swap_p1(&input)));
+        assert_eq!(p2(&input), Ok(swap_p2(&input)));
+    }
+
+    /// cargo test --release -- --ignored --nocapture bench
+    #[test]
+    #[ignore]
+    fn bench_rule_graph_vs_swapping() {
+        let input = large_input(200, 100);
+        println!(
+            "{} rules",
+            input.lines().filter(|l| l.contains('|')).count()
+        );
+
+        assert_eq!(
+            time("swap p1", || swap_p1(&input)),
+            time("graph p1", || p1(&input).unwrap())
+        );
+        assert_eq!(
+            time("swap p2", || swap_p2(&input)),
+            time("graph p2", || p2(&input).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_topological() {
+        let (rules, _) = parse(INPUT).unwrap();
+        assert_eq!(
+            rules.topological(&[97, 13, 75, 29, 47]),
+            Ok(vec![97, 75, 47, 29, 13])
+        );
+        // the example rules order every page
+        assert_eq!(
+            rules.totality(),
+            Totality::Total(vec![97, 75, 47, 61, 53, 29, 13])
+        );
+    }
+
+    #[test]
+    fn test_repeated_pages() {
+        let rules = Rules::new([(1, 2)]);
+        assert_eq!(rules.topological(&[2, 2, 1]), Ok(vec![1, 2]));
+        assert_eq!(rules.topological(&[1, 2, 1]), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let rules = Rules::new([(1, 2), (2, 3), (3, 1), (3, 4), (5, 1)]);
+        assert_eq!(
+            rules.topological(&[4, 3, 5, 2, 1]),
+            Err(Cycle(vec![1, 2, 3]))
+        );
+        assert_eq!(rules.repair(&[4, 3, 5, 2, 1]), Err(Cycle(vec![1, 2, 3])));
+        assert_eq!(rules.totality(), Totality::Cyclic(Cycle(vec![1, 2, 3])));
+        assert_eq!(
+            Cycle(vec![1, 2, 3]).to_string(),
+            "rules form a cycle: 1|2, 2|3, 3|1"
+        );
+
+        // the cycle only matters to updates containing all of its pages
+        let fixed = rules.repair(&[4, 3, 5, 1]).unwrap();
+        assert!(rules.is_ordered(&fixed));
+
+        let input = "1|2\n2|3\n3|1\n\n1,2,3\n3,2,1";
+        assert_eq!(p2(input), Err(Error::Cycle(Cycle(vec![1, 2, 3]))));
+    }
+
+    #[test]
+    fn test_partial() {
+        let rules = Rules::new([(1, 2), (1, 3), (2, 4), (3, 4)]);
+        assert_eq!(rules.totality(), Totality::Partial(2, 3));
+        // not total, but an order that breaks no rule still exists
+        let fixed = rules.repair(&[4, 3, 2, 1]).unwrap();
+        assert!(rules.is_ordered(&fixed));
+    }
+
+    #[test]
+    fn test_reports() {
+        let reports = reports(INPUT).unwrap();
+        let text: String = reports.iter().map(|r| r.to_text()).collect();
+        assert_eq!(
+            text,
+            "\
+update 0: 75,47,61,53,29 ok
+update 1: 97,61,53,29,13 ok
+update 2: 75,29,13 ok
+update 3: 75,97,47,61,53
+  breaks 97|75 (97 at 1, 75 at 0)
+  corrected 97,75,47,61,53
+  moves 1
+update 4: 61,13,29
+  breaks 29|13 (29 at 2, 13 at 1)
+  corrected 61,29,13
+  moves 1
+update 5: 97,13,75,29,47
+  breaks 75|13 (75 at 2, 13 at 1)
+  breaks 29|13 (29 at 3, 13 at 1)
+  breaks 47|13 (47 at 4, 13 at 1)
+  breaks 47|29 (47 at 4, 29 at 3)
+  corrected 97,75,47,29,13
+  moves 2
+"
+        );
+
+        assert_eq!(
+            reports[4].to_json(),
+            "{\"index\": 4, \"update\": [61, 13, 29], \"violations\": [{\"rule\": [29, 13], \"positions\": [2, 1]}], \"corrected\": [61, 29, 13], \"moves\": 1}"
+        );
+        let json = report::to_json(&reports);
+        assert!(json.starts_with("[\n  {\"index\": 0,"));
+        assert_eq!(json.lines().count(), reports.len() + 2);
+    }
+
+    #[test]
+    fn test_moves() {
+        // reversing needs everything but one page moved
+        let rules = Rules::new([(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)]);
+        assert_eq!(Report::new(&rules, 0, &[4, 3, 2, 1]).unwrap().moves, 3);
+        // a single page out of place is a single move, however far it has to go
+        assert_eq!(Report::new(&rules, 0, &[2, 3, 4, 1]).unwrap().moves, 1);
+        assert_eq!(Report::new(&rules, 0, &[1, 2, 3, 4]).unwrap().moves, 0);
+    }
+
+    #[test]
+    fn test_analyse() {
+        let (rules, _) = parse(INPUT).unwrap();
+        let analysis = analysis::analyse(&rules);
+        assert_eq!(analysis.pages, 7);
+        assert_eq!(analysis.rules, 21);
+        assert_eq!(analysis.implied, 21);
+        // a total order of 7 pages only needs the 6 rules between neighbours
+        assert_eq!(analysis.redundant.len(), 15);
+        assert!(!analysis.redundant.contains(&(97, 75)));
+        assert!(analysis.redundant.contains(&(97, 13)));
+        assert!(analysis.contradictions.is_empty());
+
+        let analysis = analysis::analyse(&Rules::new([(1, 2), (2, 3), (3, 1), (1, 3), (3, 4)]));
+        assert_eq!(
+            analysis.contradictions,
+            vec![(1, 2), (1, 3), (2, 3), (3, 1)]
+        );
+        assert_eq!(analysis.redundant, vec![(1, 3)]);
+        // 1, 2 and 3 all precede each other and 4
+        assert_eq!(analysis.implied, 9);
+    }
+
+    #[test]
+    fn test_minimise() {
+        let minimised = minimised(INPUT).unwrap();
+        let (rules, _) = parse(&minimised).unwrap();
+        assert!(rules.rules().len() < 21);
+        assert_eq!(p1(&minimised), Ok(143));
+        assert_eq!(p2(&minimised), Ok(123));
+
+        let input = large_input(40, 20);
+        let minimised = super::minimised(&input).unwrap();
+        assert!(minimised.lines().count() < input.lines().count());
+        assert_eq!(p1(&minimised), p1(&input));
+        assert_eq!(p2(&minimised), p2(&input));
+
+        // no rule applies to any update, but the output still has to parse
+        let minimised = super::minimised("1|2\n\n3,4,5").unwrap();
+        assert_eq!(minimised, "1|2\n\n3,4,5\n");
+        assert_eq!(p1(&minimised), Ok(4));
+    }
+
+    #[test]
+    fn test_parse() {
+        // Windows line endings, padding and extra blank lines are fine
+        let relaxed = format!(
+            "\n{}\n\n",
+            INPUT
+                .replace('\n', " \r\n")
+                .replace(" \r\n \r\n", "\r\n\r\n\t\r\n")
+        );
+        assert_eq!(p1(&relaxed), Ok(143));
+        assert_eq!(p2(&relaxed), Ok(123));
+
+        let error = |line, message: &str| Err(Error::Parse(ParseError::new(line, message)));
+        assert_eq!(
+            p1("1|2\n3-4\n\n1,2,3"),
+            error(2, "expected a rule X|Y, got \"3-4\"")
+        );
+        assert_eq!(
+            p1("1|2\n3|x\n\n1,2,3"),
+            error(2, "invalid page number \"x\" in \"3|x\"")
+        );
+        assert_eq!(
+            p1("1|2\n\n1,2,3\n1,,3"),
+            error(4, "invalid page number \"\" in \"1,,3\"")
+        );
+        assert_eq!(
+            p1("1|2\n\n\n1,2,3\n1,2"),
+            error(
+                5,
+                "update \"1,2\" has an even number of pages, so no middle page"
+            )
+        );
+        assert_eq!(
+            p1("1|2\n\n2,1,2"),
+            error(3, "update \"2,1,2\" lists page 2 more than once")
+        );
+        assert_eq!(p1("1|2\n3|4\n"), error(3, "missing the updates"));
+    }
 }
@@ -0,0 +1,105 @@
+//! Explaining what is wrong with each update, and how much it takes to fix it.
+
+use crate::rules::{Cycle, Rules, Violation};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// Position of the update in the input.
+    pub index: usize,
+    pub update: Vec<i32>,
+    pub violations: Vec<Violation>,
+    pub corrected: Vec<i32>,
+    /// Fewest pages that have to be taken out and put back elsewhere to get `corrected`.
+    pub moves: usize,
+}
+
+impl Report {
+    pub fn new(rules: &Rules, index: usize, update: &[i32]) -> Result<Self, Cycle> {
+        let violations = rules.violations(update);
+        let corrected = if violations.is_empty() {
+            update.to_vec()
+        } else {
+            rules.repair(update)?
+        };
+        Ok(Report {
+            index,
+            update: update.to_vec(),
+            violations,
+            moves: update.len() - longest_consistent(update, &corrected),
+            corrected,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = format!("update {}: {}", self.index, join(&self.update));
+        if self.violations.is_empty() {
+            out.push_str(" ok\n");
+            return out;
+        }
+        out.push('\n');
+        for v in &self.violations {
+            out.push_str(&format!(
+                "  breaks {}|{} ({} at {}, {} at {})\n",
+                v.rule.0, v.rule.1, v.rule.0, v.positions.0, v.rule.1, v.positions.1
+            ));
+        }
+        out.push_str(&format!("  corrected {}\n", join(&self.corrected)));
+        out.push_str(&format!("  moves {}\n", self.moves));
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let violations: Vec<String> = self
+            .violations
+            .iter()
+            .map(|v| {
+                format!(
+                    "{{\"rule\": [{}, {}], \"positions\": [{}, {}]}}",
+                    v.rule.0, v.rule.1, v.positions.0, v.positions.1
+                )
+            })
+            .collect();
+        format!(
+            "{{\"index\": {}, \"update\": [{}], \"violations\": [{}], \"corrected\": [{}], \"moves\": {}}}",
+            self.index,
+            self.update.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "),
+            violations.join(", "),
+            self.corrected.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "),
+            self.moves
+        )
+    }
+}
+
+/// All reports as a JSON array, one update per line.
+pub fn to_json(reports: &[Report]) -> String {
+    let lines: Vec<String> = reports
+        .iter()
+        .map(|r| format!("  {}", r.to_json()))
+        .collect();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+fn join(pages: &[i32]) -> String {
+    pages
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Length of the longest subsequence of `update` whose pages are already in the same relative
+/// order as in `corrected`; every other page has to move. This is the longest increasing
+/// subsequence of the pages' positions in `corrected`.
+fn longest_consistent(update: &[i32], corrected: &[i32]) -> usize {
+    // tails[k] is the smallest position that ends an increasing run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    for page in update {
+        let position = corrected.iter().position(|p| p == page).unwrap();
+        match tails.binary_search(&position) {
+            Ok(_) => {}
+            Err(k) if k == tails.len() => tails.push(position),
+            Err(k) => tails[k] = position,
+        }
+    }
+    tails.len()
+}
@@ -0,0 +1,279 @@
+//! The page ordering rules as a graph, built once and shared by every update.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// Pages whose rules contradict each other: each page must come before the next, and the last
+/// before the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle(pub Vec<i32>);
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<String> = self
+            .0
+            .iter()
+            .zip(self.0.iter().cycle().skip(1))
+            .map(|(x, y)| format!("{}|{}", x, y))
+            .collect();
+        write!(f, "rules form a cycle: {}", rules.join(", "))
+    }
+}
+
+impl std::error::Error for Cycle {}
+
+/// A rule `X|Y` broken by an update, with `Y` printed before `X`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: (i32, i32),
+    /// Positions of `X` and `Y` in the update.
+    pub positions: (usize, usize),
+}
+
+/// Two pages that the rules don't order relative to each other.
+type Unrelated = (i32, i32);
+
+/// Whether the rules order all pages they mention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Totality {
+    /// Every pair of pages is related, directly or through other pages, giving this order.
+    Total(Vec<i32>),
+    /// No cycles, but nothing orders these two pages relative to each other.
+    Partial(i32, i32),
+    Cyclic(Cycle),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    /// `X|Y` is stored as `Y` in `after[X]`.
+    after: HashMap<i32, HashSet<i32>>,
+}
+
+impl Rules {
+    pub fn new(rules: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        let mut after: HashMap<i32, HashSet<i32>> = HashMap::new();
+        for (x, y) in rules {
+            after.entry(x).or_default().insert(y);
+        }
+        Rules { after }
+    }
+
+    /// Every rule `X|Y` as `(X, Y)`, sorted.
+    pub fn rules(&self) -> Vec<(i32, i32)> {
+        let mut rules: Vec<(i32, i32)> = self
+            .after
+            .iter()
+            .flat_map(|(&x, after)| after.iter().map(move |&y| (x, y)))
+            .collect();
+        rules.sort();
+        rules
+    }
+
+    /// Every page mentioned by a rule, sorted.
+    pub fn pages(&self) -> Vec<i32> {
+        let mut pages: Vec<i32> = self
+            .after
+            .iter()
+            .flat_map(|(&x, after)| std::iter::once(x).chain(after.iter().copied()))
+            .collect::<HashSet<i32>>()
+            .into_iter()
+            .collect();
+        pages.sort();
+        pages
+    }
+
+    /// Pages reachable from `x` through one or more rules, only passing through pages accepted by
+    /// `allowed` and never using the rule `skip`.
+    pub fn reachable(
+        &self,
+        x: i32,
+        allowed: impl Fn(i32) -> bool,
+        skip: Option<(i32, i32)>,
+    ) -> HashSet<i32> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![x];
+        while let Some(current) = stack.pop() {
+            for next in self.successors(current) {
+                if Some((current, next)) != skip && allowed(next) && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Whether there is a rule `x|y`.
+    pub fn before(&self, x: i32, y: i32) -> bool {
+        self.after.get(&x).is_some_and(|after| after.contains(&y))
+    }
+
+    /// Pages that must come after `x`, if both are in the update.
+    pub fn successors(&self, x: i32) -> impl Iterator<Item = i32> + '_ {
+        self.after.get(&x).into_iter().flatten().copied()
+    }
+
+    /// Whether `update` breaks none of the rules.
+    pub fn is_ordered(&self, update: &[i32]) -> bool {
+        let position: HashMap<i32, usize> = update
+            .iter()
+            .enumerate()
+            .map(|(i, &page)| (page, i))
+            .collect();
+
+        update.iter().enumerate().all(|(i, &x)| {
+            self.successors(x)
+                .all(|y| position.get(&y).is_none_or(|&j| j > i))
+        })
+    }
+
+    /// Every rule `update` breaks, in the order the pages appear in the update.
+    pub fn violations(&self, update: &[i32]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (j, &y) in update.iter().enumerate() {
+            for (i, &x) in update.iter().enumerate().skip(j + 1) {
+                if self.before(x, y) {
+                    violations.push(Violation {
+                        rule: (x, y),
+                        positions: (i, j),
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    /// Ordering of two pages according to the rules, `Equal` if no rule relates them.
+    pub fn compare(&self, a: i32, b: i32) -> Ordering {
+        if self.before(a, b) {
+            Ordering::Less
+        } else if self.before(b, a) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    /// Put `update` in the order given by the rules. This relies on the rules relating every pair
+    /// of pages in the update without contradicting each other, as they do in the puzzle input;
+    /// otherwise the comparator isn't a total order, and sorting may panic.
+    pub fn sort(&self, update: &mut [i32]) {
+        update.sort_by(|&a, &b| self.compare(a, b));
+    }
+
+    /// Whether the rules between `pages` are a strict total order, so that [`Rules::sort`] can be
+    /// used: every pair is related one way, and there are no cycles. In such a tournament the
+    /// number of pages that have to come before a page differs for every page.
+    pub fn totally_orders(&self, pages: &[i32]) -> bool {
+        let mut predecessors = Vec::with_capacity(pages.len());
+        for (j, &y) in pages.iter().enumerate() {
+            let mut count = 0;
+            for (i, &x) in pages.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                match (self.before(x, y), self.before(y, x)) {
+                    (true, false) => count += 1,
+                    (false, true) => {}
+                    _ => return false,
+                }
+            }
+            predecessors.push(count);
+        }
+        predecessors.sort();
+        predecessors.into_iter().eq(0..pages.len())
+    }
+
+    /// `update` in an order that breaks none of the rules. Sorting suffices when the rules
+    /// totally order the update; when they don't, fall back to [`Rules::topological`], which
+    /// either finds an order or the cycle that prevents one.
+    pub fn repair(&self, update: &[i32]) -> Result<Vec<i32>, Cycle> {
+        if self.totally_orders(update) {
+            let mut sorted = update.to_vec();
+            self.sort(&mut sorted);
+            Ok(sorted)
+        } else {
+            self.topological(update)
+        }
+    }
+
+    /// Order `pages` with Kahn's algorithm, preferring the given order among unrelated pages.
+    pub fn topological(&self, pages: &[i32]) -> Result<Vec<i32>, Cycle> {
+        self.kahn(pages).map(|(order, _)| order)
+    }
+
+    /// Whether the rules, taken together, order every page that appears in them.
+    pub fn totality(&self) -> Totality {
+        match self.kahn(&self.pages()) {
+            Ok((order, None)) => Totality::Total(order),
+            Ok((_, Some((a, b)))) => Totality::Partial(a, b),
+            Err(cycle) => Totality::Cyclic(cycle),
+        }
+    }
+
+    /// Kahn's algorithm over the rules restricted to `pages`. Along with the order, returns the
+    /// first two pages found that could have been output in either order, if any: the order is
+    /// unique exactly when there are none. A page listed more than once is only output once.
+    fn kahn(&self, pages: &[i32]) -> Result<(Vec<i32>, Option<Unrelated>), Cycle> {
+        let mut included = HashSet::new();
+        let pages: Vec<i32> = pages
+            .iter()
+            .copied()
+            .filter(|&p| included.insert(p))
+            .collect();
+        let pages = &pages[..];
+        let mut in_degree: HashMap<i32, usize> = pages.iter().map(|&p| (p, 0)).collect();
+        for &x in &included {
+            for y in self.successors(x).filter(|y| included.contains(y)) {
+                *in_degree.get_mut(&y).unwrap() += 1;
+            }
+        }
+
+        let mut ready: VecDeque<i32> = pages
+            .iter()
+            .copied()
+            .filter(|p| in_degree[p] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(pages.len());
+        let mut ambiguous = None;
+
+        while let Some(x) = ready.pop_front() {
+            if let (None, Some(&y)) = (ambiguous, ready.front()) {
+                ambiguous = Some((x, y));
+            }
+            order.push(x);
+            // successors in the given order, to keep the result deterministic
+            for &y in pages {
+                if self.before(x, y) {
+                    let degree = in_degree.get_mut(&y).unwrap();
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push_back(y);
+                    }
+                }
+            }
+        }
+
+        if order.len() == pages.len() {
+            return Ok((order, ambiguous));
+        }
+
+        // Every page left over has a predecessor that is left over too, so walking backwards
+        // from any of them must eventually revisit a page.
+        let left: Vec<i32> = pages.iter().copied().filter(|p| in_degree[p] > 0).collect();
+        let mut path = vec![left[0]];
+        loop {
+            let current = *path.last().unwrap();
+            let prev = *left.iter().find(|&&p| self.before(p, current)).unwrap();
+            if let Some(start) = path.iter().position(|&p| p == prev) {
+                let mut cycle = path.split_off(start);
+                cycle.reverse();
+                // start the cycle at its smallest page
+                let min = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap();
+                cycle.rotate_left(min);
+                return Err(Cycle(cycle));
+            }
+            path.push(prev);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Watching the guard walk, one step per frame, redrawn in place with ANSI escapes.
+//!
+//! Commands are read a line at a time from stdin (type the letter, then Enter):
+//! `p` pauses or resumes, `s` takes a single step and pauses, `l` jumps ahead to the moment the
+//! guard leaves or is caught in a loop, and `q` quits.
+
+use crate::map::{Heading, Map, Pos};
+use crate::patrol::{self, Bitset, Walk};
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Walking,
+    Left,
+    /// Back in a state she has been in before.
+    Looped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Pause,
+    Step,
+    JumpToEnd,
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Self> {
+        match line.trim() {
+            "p" => Some(Command::Pause),
+            "s" => Some(Command::Step),
+            "l" => Some(Command::JumpToEnd),
+            "q" => Some(Command::Quit),
+            _ => None,
+        }
+    }
+}
+
+pub struct Animation<'a> {
+    map: &'a Map,
+    obstacle: Option<Pos>,
+    walk: Walk<'a>,
+    pub tick: usize,
+    pub pos: Pos,
+    pub heading: Heading,
+    pub status: Status,
+    trail: Bitset,
+}
+
+impl<'a> Animation<'a> {
+    pub fn new(map: &'a Map, obstacle: Option<Pos>) -> Self {
+        let mut animation = Animation {
+            map,
+            obstacle,
+            walk: patrol::walk(map, map.start, map.heading, obstacle),
+            tick: 0,
+            pos: map.start,
+            heading: map.heading,
+            status: Status::Walking,
+            trail: Bitset::new(map.height * map.width),
+        };
+        animation.walk.next();
+        animation
+            .trail
+            .insert(map.start.0 * map.width + map.start.1);
+        animation
+    }
+
+    /// Move forward or turn, unless the walk is already over.
+    pub fn step(&mut self) {
+        if self.status != Status::Walking {
+            return;
+        }
+        self.tick += 1;
+        match (self.walk.next(), self.walk.repeated) {
+            (Some((pos, heading)), _) => {
+                (self.pos, self.heading) = (pos, heading);
+                self.trail.insert(pos.0 * self.map.width + pos.1);
+            }
+            (None, Some((pos, heading))) => {
+                (self.pos, self.heading) = (pos, heading);
+                self.status = Status::Looped;
+            }
+            (None, None) => self.status = Status::Left,
+        }
+    }
+
+    pub fn jump_to_end(&mut self) {
+        while self.status == Status::Walking {
+            self.step();
+        }
+    }
+
+    /// The map with the trail dimmed, the guard in bold yellow and the placed obstacle in red,
+    /// under a line saying where she is.
+    pub fn frame(&self) -> String {
+        let mut frame = format!(
+            "tick {:<6} {:?} facing {:?}{}\x1b[K\n",
+            self.tick,
+            self.pos,
+            self.heading,
+            match self.status {
+                Status::Walking => "",
+                Status::Left => "  -- left the map",
+                Status::Looped => "  -- loop detected",
+            }
+        );
+        for row in 0..self.map.height {
+            for col in 0..self.map.width {
+                let pos = (row, col);
+                if pos == self.pos && self.status != Status::Left {
+                    frame += &format!("\x1b[1;33m{}\x1b[0m", self.heading.symbol() as char);
+                } else if Some(pos) == self.obstacle {
+                    frame += "\x1b[1;31mO\x1b[0m";
+                } else if self.map.is_obstacle(pos) {
+                    frame.push('#');
+                } else if self.trail.contains(row * self.map.width + col) {
+                    frame += "\x1b[2mX\x1b[0m";
+                } else {
+                    frame.push('.');
+                }
+            }
+            frame.push('\n');
+        }
+        frame
+    }
+}
+
+/// Commands typed on stdin, read on a separate thread so the animation doesn't block on them.
+pub fn stdin_commands() -> Receiver<Command> {
+    let (send, receive) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if let Some(command) = Command::parse(&line) {
+                if send.send(command).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    receive
+}
+
+/// Play the animation at `fps` frames a second, clearing the terminal before each frame. The
+/// cursor is shown again at the end, even if drawing fails partway.
+pub fn play(
+    animation: &mut Animation,
+    fps: f64,
+    commands: Receiver<Command>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let played = draw(animation, fps, commands, out);
+    let restored = writeln!(out, "\x1b[?25h").and_then(|_| out.flush());
+    played.and(restored)
+}
+
+fn draw(
+    animation: &mut Animation,
+    fps: f64,
+    commands: Receiver<Command>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let delay = Duration::from_secs_f64(1.0 / fps);
+    let mut paused = false;
+    // hide the cursor while drawing
+    write!(out, "\x1b[?25l\x1b[2J\x1b[H{}", animation.frame())?;
+    out.flush()?;
+
+    while animation.status == Status::Walking {
+        let command = if paused {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                // nothing more can be typed to resume once stdin is closed
+                Err(_) => {
+                    paused = false;
+                    None
+                }
+            }
+        } else {
+            match commands.recv_timeout(delay) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(delay);
+                    None
+                }
+            }
+        };
+
+        match command {
+            None if !paused => animation.step(),
+            None => {}
+            Some(Command::Pause) => paused = !paused,
+            Some(Command::Step) => {
+                paused = true;
+                animation.step();
+            }
+            Some(Command::JumpToEnd) => animation.jump_to_end(),
+            Some(Command::Quit) => break,
+        }
+        write!(out, "\x1b[H{}", animation.frame())?;
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,335 @@
+//! Every guard on the map patrolling at once, each turning by the same policy when blocked.
+//!
+//! Guards move in lockstep: each tick, every guard still on the map either steps forward or, if
+//! an obstacle is in the way, turns on the spot. Guards don't block one another, but meeting in a
+//! cell (or passing through each other) is a collision, and stepping onto a cell another guard
+//! has already visited is entering her path.
+
+use crate::map::{Heading, Map, Pos};
+use crate::patrol::Bitset;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnPolicy {
+    Right,
+    Left,
+    Reverse,
+    /// Right, then left, then right again, and so on.
+    Alternate,
+}
+
+impl TurnPolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "right" => Some(TurnPolicy::Right),
+            "left" => Some(TurnPolicy::Left),
+            "reverse" => Some(TurnPolicy::Reverse),
+            "alternate" => Some(TurnPolicy::Alternate),
+            _ => None,
+        }
+    }
+
+    /// How many turns it takes before the policy repeats itself.
+    fn phases(self) -> usize {
+        match self {
+            TurnPolicy::Alternate => 2,
+            _ => 1,
+        }
+    }
+
+    fn turn(self, heading: Heading, phase: usize) -> Heading {
+        match (self, phase) {
+            (TurnPolicy::Right, _) | (TurnPolicy::Alternate, 0) => heading.turn_right(),
+            (TurnPolicy::Left, _) | (TurnPolicy::Alternate, _) => heading.turn_left(),
+            (TurnPolicy::Reverse, _) => heading.reverse(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fate {
+    /// Stepped off the map on this tick.
+    Leaves { tick: usize },
+    /// Got back to an earlier state on this tick, repeating every `period` ticks from then on.
+    Loops { tick: usize, period: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Guard {
+    pub start: (Pos, Heading),
+    /// Distinct cells visited, in the order first visited.
+    pub visited: Vec<Pos>,
+    pub fate: Fate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Two guards ended up in the same cell, or swapped cells, on the same tick.
+    Collision {
+        tick: usize,
+        pos: Pos,
+        guards: (usize, usize),
+    },
+    /// `guard` stepped onto a cell `other` had visited on an earlier tick.
+    Crossing {
+        tick: usize,
+        pos: Pos,
+        guard: usize,
+        other: usize,
+    },
+}
+
+/// How far past the point every guard's fate is known a pair of looping guards is followed,
+/// looking for a collision.
+pub const HORIZON: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patrols {
+    pub guards: Vec<Guard>,
+    /// The first collision of each pair of guards and the first time each guard enters each
+    /// other guard's path, in the order they happen.
+    pub events: Vec<Event>,
+    /// Pairs of looping guards whose loops only line up again after more than [`HORIZON`]
+    /// ticks, so that a collision between them any later than that would have been missed.
+    pub unresolved: Vec<(usize, usize)>,
+}
+
+impl Event {
+    fn tick(&self) -> usize {
+        match *self {
+            Event::Collision { tick, .. } | Event::Crossing { tick, .. } => tick,
+        }
+    }
+}
+
+struct Walker {
+    pos: Pos,
+    heading: Heading,
+    phase: usize,
+    on_map: bool,
+    /// The tick each `(cell, heading, phase)` state was first reached, `usize::MAX` if never.
+    first_seen: Vec<usize>,
+    /// Where she was on each tick while still on the map.
+    trail: Vec<Pos>,
+    cells: Bitset,
+    visited: Vec<Pos>,
+    fate: Option<Fate>,
+}
+
+impl Walker {
+    fn state(&self, map: &Map, phases: usize) -> usize {
+        ((self.pos.0 * map.width + self.pos.1) * 4 + self.heading.index()) * phases + self.phase
+    }
+
+    fn period(&self) -> Option<usize> {
+        match self.fate {
+            Some(Fate::Loops { period, .. }) => Some(period),
+            _ => None,
+        }
+    }
+
+    /// Where she is on `tick`, however far ahead of the trail, once she is known to loop.
+    fn pos_at(&self, tick: usize) -> Pos {
+        match self.fate {
+            Some(Fate::Loops {
+                tick: repeat,
+                period,
+            }) if tick >= repeat => {
+                let start = repeat - period;
+                self.trail[start + (tick - start) % period]
+            }
+            _ => self.trail[tick],
+        }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Run every guard on `map` until each has either left or fallen into a loop, then follow each
+/// looping guard round her loop, and each pair of them until their loops line up again, so that
+/// every collision that will ever happen has been seen.
+pub fn patrol(map: &Map, policy: TurnPolicy) -> Patrols {
+    let phases = policy.phases();
+    let cells = map.height * map.width;
+    let mut walkers: Vec<Walker> = map
+        .guards
+        .iter()
+        .map(|&(pos, heading)| {
+            let mut walker = Walker {
+                pos,
+                heading,
+                phase: 0,
+                on_map: true,
+                first_seen: vec![usize::MAX; cells * 4 * phases],
+                trail: vec![pos],
+                cells: Bitset::new(cells),
+                visited: vec![pos],
+                fate: None,
+            };
+            let state = walker.state(map, phases);
+            walker.first_seen[state] = 0;
+            walker.cells.insert(pos.0 * map.width + pos.1);
+            walker
+        })
+        .collect();
+
+    let mut events = Vec::new();
+    let mut collided = HashSet::new();
+    let mut crossed = HashSet::new();
+    let mut tick = 0;
+
+    while walkers.iter().any(|w| w.fate.is_none()) {
+        tick += 1;
+
+        let before: Vec<Pos> = walkers.iter().map(|w| w.pos).collect();
+        let mut moved = vec![false; walkers.len()];
+        for (i, w) in walkers.iter_mut().enumerate().filter(|(_, w)| w.on_map) {
+            match map.step(w.pos, w.heading) {
+                None => {
+                    w.on_map = false;
+                    w.fate.get_or_insert(Fate::Leaves { tick });
+                    continue;
+                }
+                Some(next) if map.is_obstacle(next) => {
+                    w.heading = policy.turn(w.heading, w.phase);
+                    w.phase = (w.phase + 1) % phases;
+                }
+                Some(next) => {
+                    w.pos = next;
+                    moved[i] = true;
+                }
+            }
+            w.trail.push(w.pos);
+
+            let state = w.state(map, phases);
+            match w.first_seen[state] {
+                usize::MAX => w.first_seen[state] = tick,
+                seen if w.fate.is_none() => {
+                    w.fate = Some(Fate::Loops {
+                        tick,
+                        period: tick - seen,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        // compare against everyone's paths as they were before this tick's moves
+        for i in (0..walkers.len()).filter(|&i| moved[i]) {
+            let pos = walkers[i].pos;
+            for j in (0..walkers.len()).filter(|&j| j != i) {
+                if walkers[j].cells.contains(pos.0 * map.width + pos.1) && crossed.insert((i, j)) {
+                    events.push(Event::Crossing {
+                        tick,
+                        pos,
+                        guard: i,
+                        other: j,
+                    });
+                }
+            }
+        }
+        for i in (0..walkers.len()).filter(|&i| moved[i]) {
+            let w = &mut walkers[i];
+            if w.cells.insert(w.pos.0 * map.width + w.pos.1) {
+                w.visited.push(w.pos);
+            }
+        }
+
+        for i in 0..walkers.len() {
+            for j in i + 1..walkers.len() {
+                let (a, b) = (&walkers[i], &walkers[j]);
+                if !(a.on_map && b.on_map) {
+                    continue;
+                }
+                let meet = a.pos == b.pos;
+                let swap = a.pos == before[j] && b.pos == before[i] && a.pos != b.pos;
+                if (meet || swap) && collided.insert((i, j)) {
+                    events.push(Event::Collision {
+                        tick,
+                        pos: a.pos,
+                        guards: (i, j),
+                    });
+                }
+            }
+        }
+    }
+
+    // Every guard still on the map now goes round and round her loop, and every path is
+    // complete, so a guard enters another's path within one more loop or never does.
+    let end = tick;
+    let looping: Vec<usize> = (0..walkers.len()).filter(|&i| walkers[i].on_map).collect();
+    for &i in &looping {
+        let period = walkers[i].period().unwrap();
+        for tick in end + 1..=end + period {
+            let pos = walkers[i].pos_at(tick);
+            if pos == walkers[i].pos_at(tick - 1) {
+                continue;
+            }
+            for j in (0..walkers.len()).filter(|&j| j != i) {
+                if walkers[j].cells.contains(pos.0 * map.width + pos.1) && crossed.insert((i, j)) {
+                    events.push(Event::Crossing {
+                        tick,
+                        pos,
+                        guard: i,
+                        other: j,
+                    });
+                }
+            }
+        }
+    }
+
+    // Two looping guards are back where they were together every lcm of their periods.
+    let mut unresolved = Vec::new();
+    for (k, &i) in looping.iter().enumerate() {
+        for &j in looping[k + 1..]
+            .iter()
+            .filter(|&&j| !collided.contains(&(i, j)))
+        {
+            let (a, b) = (&walkers[i], &walkers[j]);
+            let (pa, pb) = (a.period().unwrap(), b.period().unwrap());
+            let horizon = match (pa / gcd(pa, pb)).checked_mul(pb) {
+                Some(lcm) if lcm <= HORIZON => lcm,
+                _ => {
+                    unresolved.push((i, j));
+                    HORIZON
+                }
+            };
+            for tick in end + 1..=end + horizon {
+                let (pos, other) = (a.pos_at(tick), b.pos_at(tick));
+                let swap = pos == b.pos_at(tick - 1) && other == a.pos_at(tick - 1);
+                if pos == other || swap {
+                    events.push(Event::Collision {
+                        tick,
+                        pos,
+                        guards: (i, j),
+                    });
+                    break;
+                }
+            }
+        }
+    }
+    // within a tick, crossings come before collisions, as they do above
+    events.sort_by_key(|e| (e.tick(), matches!(e, Event::Collision { .. })));
+
+    let guards = map
+        .guards
+        .iter()
+        .zip(walkers)
+        .map(|(&start, w)| Guard {
+            start,
+            visited: w.visited,
+            fate: w.fate.unwrap(),
+        })
+        .collect();
+    Patrols {
+        guards,
+        events,
+        unresolved,
+    }
+}
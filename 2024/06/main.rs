@@ -110,65 +110,23 @@ Predict the path of the guard. How many distinct positions will the guard visit
 mapped area?
 */
 
-use std::collections::{HashMap, HashSet};
+mod animate;
+mod guards;
+mod map;
+mod patrol;
+mod render;
+
+use guards::{Event, Fate, TurnPolicy};
+use map::{Map, Pos};
+use patrol::{Cycle, Outcome};
+use std::env;
 use std::fs;
 use std::io::{self};
+use std::thread;
 
 pub fn p1(input: &str) -> i32 {
-    let lines: Vec<String> = input.lines().map(|l| l.to_string()).collect();
-
-    for line in &lines {
-        println!("{:?}", line);
-    }
-
-    let h = lines.len() as i32;
-    let w = lines[0].len() as i32;
-
-    // Derive current position of the guard
-    let mut pos: (i32, i32) = (0, 0);
-    for (j, line) in lines.iter().enumerate() {
-        if let Some(i) = line.find('^') {
-            pos = (j as i32, i as i32);
-        }
-    }
-
-    fn in_bounds(pos: (i32, i32), h: i32, w: i32) -> bool {
-        pos.0 >= 0 && pos.0 < h && pos.1 >= 0 && pos.1 < w
-    }
-
-    let mut dir: usize = 0;
-    let mut unique_pos: HashSet<(i32, i32)> = HashSet::new();
-    unique_pos.insert(pos); // include starting position
-
-    loop {
-        let prev = pos.clone();
-        match dir % 4 {
-            0 => pos.0 -= 1, // ^
-            1 => pos.1 += 1, // >
-            2 => pos.0 += 1, // v
-            3 => pos.1 -= 1, // <
-            _ => panic!("Invalid direction"),
-        }
-
-        println!("{:?}", pos);
-
-        if !in_bounds(pos, h, w) {
-            break;
-        }
-
-        println!(
-            "{:?}",
-            (pos, h, w, lines[pos.0 as usize].chars().nth(pos.1 as usize))
-        );
-        if lines[pos.0 as usize].chars().nth(pos.1 as usize) == Some('#') {
-            dir += 1;
-            pos = prev; // revert position
-        }
-
-        unique_pos.insert(pos);
-    }
-
-    unique_pos.len() as i32
+    let map = Map::parse(input);
+    patrol::path(&map, map.start, map.heading).len() as i32
 }
 
 /*
@@ -281,84 +239,67 @@ You need to get the guard stuck in a loop by adding a single new obstruction. Ho
 positions could you choose for this obstruction?
 */
 
-pub fn p2(input: &str) -> i32 {
-    let lines: Vec<String> = input.lines().map(|l| l.to_string()).collect();
-
-    let h = lines.len() as i32;
-    let w = lines[0].len() as i32;
-
-    //fn find_starting_pos(lines: &Vec<String>) {
-    //}
-
-    // Derive current position of the guard
-    let mut pos: (i32, i32) = (0, 0);
-    for (j, line) in lines.iter().enumerate() {
-        if let Some(i) = line.find('^') {
-            pos = (j as i32, i as i32);
-        }
-    }
-
-    fn in_bounds(pos: (i32, i32), h: i32, w: i32) -> bool {
-        pos.0 >= 0 && pos.0 < h && pos.1 >= 0 && pos.1 < w
-    }
-
-    let mut infinite_counter = 0;
-    let starting_pos = pos.clone();
-
-    // We love a good brute force...
-    for ix in 0..h * w {
-        let mock_block_pos = (ix / w, ix % w);
-
-        println!("{:?}", (mock_block_pos, h, w, infinite_counter));
-
-        // Skip replacement of starting position
-        if mock_block_pos == starting_pos {
-            continue;
-        }
+/*
 
-        let mut dir: usize = 0;
-        let mut visited: HashMap<(i32, i32), usize> = HashMap::new();
-        pos = starting_pos;
+cargo run
+cargo run -- --threads 4
+cargo run -- --loops
+cargo run -- --render
+cargo run -- --render 6,3
+cargo run -- --guards alternate
+cargo run -- --animate 6,3 --fps 20
 
-        let mut fallback_counter = 0;
-        loop {
-            if fallback_counter > 1_000_000 {
-                println!("Fallback infinite loop, what's going on...");
-                infinite_counter += 1;
-                break;
-            }
-            fallback_counter += 1;
-
-            visited.insert(pos, dir % 4); // starting point
-            let prev = pos.clone();
-            match dir % 4 {
-                0 => pos.0 -= 1, // ^
-                1 => pos.1 += 1, // >
-                2 => pos.0 += 1, // v
-                3 => pos.1 -= 1, // <
-                _ => panic!("Invalid direction"),
-            }
+cargo test --release -- --ignored --nocapture bench
 
-            if !in_bounds(pos, h, w) {
-                break;
-            }
+*/
 
-            let c = lines[pos.0 as usize].chars().nth(pos.1 as usize);
-            if c == Some('#') || (pos == mock_block_pos) {
-                dir += 1;
-                pos = prev; // revert position
-            }
+/// Number of threads to use unless told otherwise.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
 
-            // check if we have already visited the square, and are moving in the same direction; this
-            // indicates that we're in an infinite loop...
-            if visited.get(&pos) == Some(&(dir % 4)) {
-                infinite_counter += 1;
-                break;
-            }
-        }
+/// Every obstacle position that traps the guard, with the loop she ends up in, in the order the
+/// guard first reaches each position. Candidates are split into contiguous chunks across
+/// `threads` scoped threads, and the chunks' results joined back in order.
+pub fn loops(map: &Map, threads: usize) -> Vec<(Pos, Cycle)> {
+    // an obstacle off the original path would never be run into
+    let candidates: Vec<Pos> = patrol::path(map, map.start, map.heading)
+        .into_iter()
+        .filter(|&pos| pos != map.start)
+        .collect();
+
+    let check = |chunk: &[Pos]| -> Vec<(Pos, Cycle)> {
+        chunk
+            .iter()
+            .filter_map(
+                |&pos| match patrol::simulate(map, map.start, map.heading, Some(pos)) {
+                    Outcome::Loops(cycle) => Some((pos, cycle)),
+                    Outcome::Exits => None,
+                },
+            )
+            .collect()
+    };
+
+    let threads = threads.clamp(1, candidates.len().max(1));
+    if threads == 1 {
+        return check(&candidates);
     }
 
-    infinite_counter
+    let chunk_size = candidates.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || check(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+pub fn p2(input: &str) -> i32 {
+    loops(&Map::parse(input), default_threads()).len() as i32
 }
 
 pub static INPUT: &str = "\
@@ -385,20 +326,593 @@ pub static INPUT2: &str = "\
 #.........
 ......#...";
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn usage(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
 
-    #[test]
-    fn test_p1p2() {
-        //assert_eq!(p1(&INPUT), 41);
-        assert_eq!(p2(&INPUT), 6);
+/// A `row,col` position given on the command line.
+fn parse_pos(arg: &str) -> Option<Pos> {
+    let (row, col) = arg.split_once(',')?;
+    Some((row.parse().ok()?, col.parse().ok()?))
+}
+
+/// The position given on the command line after `flag`, which has to be on `map`.
+fn position(flag: &str, arg: &str, map: &Map) -> io::Result<Pos> {
+    let (row, col) = parse_pos(arg).ok_or_else(|| usage(&format!("{} takes row,col", flag)))?;
+    if row >= map.height || col >= map.width {
+        return Err(usage(&format!(
+            "{} {},{} is off the {}x{} map",
+            flag, row, col, map.height, map.width
+        )));
     }
+    Ok((row, col))
 }
 
 fn main() -> io::Result<()> {
     let input = fs::read_to_string("input.txt")?;
+    let args: Vec<String> = env::args().collect();
+    let threads = match args.iter().position(|a| a == "--threads") {
+        Some(i) => args
+            .get(i + 1)
+            .and_then(|n| n.parse().ok())
+            .filter(|&n| n > 0)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--threads takes a positive count",
+                )
+            })?,
+        None => default_threads(),
+    };
+
+    if let Some(i) = args.iter().position(|a| a == "--render") {
+        let map = Map::parse(&input);
+        match args.get(i + 1) {
+            Some(pos) => {
+                let obstacle = position("--render", pos, &map)?;
+                print!("{}", render::route(&map, Some(obstacle)));
+            }
+            None => print!("{}", render::visited(&map)),
+        }
+        return Ok(());
+    }
+
+    if let Some(i) = args.iter().position(|a| a == "--animate") {
+        let map = Map::parse(&input);
+        let obstacle = match args.get(i + 1).filter(|a| !a.starts_with("--")) {
+            Some(pos) => Some(position("--animate", pos, &map)?),
+            None => None,
+        };
+        let fps = match args.iter().position(|a| a == "--fps") {
+            Some(i) => args
+                .get(i + 1)
+                .and_then(|n| n.parse().ok())
+                .filter(|&n: &f64| n > 0.0)
+                .ok_or_else(|| usage("--fps takes a positive rate"))?,
+            None => 10.0,
+        };
+        let mut animation = animate::Animation::new(&map, obstacle);
+        return animate::play(
+            &mut animation,
+            fps,
+            animate::stdin_commands(),
+            &mut io::stdout(),
+        );
+    }
+
+    if let Some(i) = args.iter().position(|a| a == "--guards") {
+        let policy = match args.get(i + 1) {
+            Some(name) => TurnPolicy::parse(name)
+                .ok_or_else(|| usage("--guards takes right, left, reverse or alternate"))?,
+            None => TurnPolicy::Right,
+        };
+        let patrols = guards::patrol(&Map::parse(&input), policy);
+        for (i, guard) in patrols.guards.iter().enumerate() {
+            let ((row, col), heading) = guard.start;
+            let fate = match guard.fate {
+                Fate::Leaves { tick } => format!("leaves after {} ticks", tick),
+                Fate::Loops { tick, period } => {
+                    format!("loops every {} ticks, from tick {}", period, tick - period)
+                }
+            };
+            println!(
+                "guard {} from ({}, {}) {:?}: {} cells, {}",
+                i,
+                row,
+                col,
+                heading,
+                guard.visited.len(),
+                fate
+            );
+        }
+        for event in patrols.events {
+            match event {
+                Event::Collision { tick, pos, guards } => println!(
+                    "tick {}: guards {} and {} collide at {:?}",
+                    tick, guards.0, guards.1, pos
+                ),
+                Event::Crossing {
+                    tick,
+                    pos,
+                    guard,
+                    other,
+                } => println!(
+                    "tick {}: guard {} enters guard {}'s path at {:?}",
+                    tick, guard, other, pos
+                ),
+            }
+        }
+        for (a, b) in patrols.unresolved {
+            println!(
+                "guards {} and {} only line up again after more than {} ticks; stopped looking for a collision",
+                a,
+                b,
+                guards::HORIZON
+            );
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|a| a == "--loops") {
+        for (obstacle, cycle) in loops(&Map::parse(&input), threads) {
+            let (entry, heading) = cycle.entry;
+            println!(
+                "obstacle at {:?}: loop of {} steps, entered at {:?} heading {:?}",
+                obstacle, cycle.length, entry, heading
+            );
+        }
+    }
     println!("p1 {}", p1(&input));
-    println!("p2 {}", p2(&input));
+    println!("p2 {}", loops(&Map::parse(&input), threads).len());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_common::testing::{time, XorShift};
+    use map::Heading;
+    use std::sync::mpsc;
+
+    /// A map of `height` rows by `width` columns with roughly one cell in `density` blocked and
+    /// the guard near the bottom middle.
+    fn random_map(seed: u64, height: usize, width: usize, density: u64) -> String {
+        let mut rng = XorShift::new(seed);
+        let mut input = String::new();
+        for row in 0..height {
+            for col in 0..width {
+                let blocked = rng.below(density) == 0;
+                input.push(match (row, col) {
+                    _ if (row, col) == (height * 2 / 3, width / 2) => '^',
+                    _ if blocked => '#',
+                    _ => '.',
+                });
+            }
+            input.push('\n');
+        }
+        input
+    }
+
+    #[test]
+    fn test_p1p2() {
+        assert_eq!(p1(INPUT), 41);
+        assert_eq!(p2(INPUT), 6);
+    }
+
+    #[test]
+    fn test_obstacle_ahead() {
+        let map = Map::parse(INPUT);
+        assert_eq!(
+            map.obstacle_ahead(map.start, map.heading, None),
+            Some((0, 4))
+        );
+        assert_eq!(
+            map.obstacle_ahead((1, 4), Heading::Right, None),
+            Some((1, 9))
+        );
+        assert_eq!(
+            map.obstacle_ahead((1, 8), Heading::Down, None),
+            Some((7, 8))
+        );
+        assert_eq!(map.obstacle_ahead((9, 7), Heading::Down, None), None);
+        // a placed obstacle only counts if it's nearer than the existing one
+        assert_eq!(
+            map.obstacle_ahead(map.start, map.heading, Some((3, 4))),
+            Some((3, 4))
+        );
+        assert_eq!(
+            map.obstacle_ahead((1, 4), Heading::Right, Some((1, 2))),
+            Some((1, 9))
+        );
+        assert_eq!(
+            map.obstacle_ahead(map.start, Heading::Left, Some((6, 3))),
+            Some((6, 3))
+        );
+    }
+
+    /// Step-by-step reference: the first repeated state is where the loop closes.
+    fn naive(map: &Map, extra: Pos) -> Outcome {
+        let mut walk = patrol::walk(map, map.start, map.heading, Some(extra));
+        let states: Vec<(Pos, Heading)> = walk.by_ref().collect();
+        let Some(entry) = walk.repeated else {
+            return Outcome::Exits;
+        };
+        let k = states.iter().position(|&s| s == entry).unwrap();
+        let lap: Vec<Pos> = states[k..]
+            .iter()
+            .map(|&(pos, _)| pos)
+            .chain([entry.0])
+            .collect();
+        Outcome::Loops(Cycle {
+            entry,
+            length: lap.windows(2).filter(|w| w[0] != w[1]).count(),
+        })
+    }
+
+    #[test]
+    fn test_cycles() {
+        let map = Map::parse(INPUT);
+        let loops = loops(&map, 1);
+        let obstacles: Vec<Pos> = loops.iter().map(|&(o, _)| o).collect();
+        assert_eq!(
+            obstacles,
+            vec![(6, 3), (7, 6), (8, 3), (8, 1), (7, 7), (9, 7)]
+        );
+
+        // option one: up, right, down and back left to the start, 5 + 4 + 5 + 4 steps
+        assert_eq!(
+            loops[0].1,
+            Cycle {
+                entry: ((6, 4), Heading::Up),
+                length: 18
+            }
+        );
+
+        for (obstacle, cycle) in loops {
+            assert_eq!(
+                naive(&map, obstacle),
+                Outcome::Loops(cycle),
+                "{:?}",
+                obstacle
+            );
+        }
+    }
+
+    #[test]
+    fn test_cycles_random_maps() {
+        for seed in 0..100 {
+            let input = random_map(0x0606 + seed, 12, 15, 7);
+
+            let map = Map::parse(&input);
+            for row in 0..map.height {
+                for col in 0..map.width {
+                    if (row, col) == map.start || map.is_obstacle((row, col)) {
+                        continue;
+                    }
+                    assert_eq!(
+                        patrol::simulate(&map, map.start, map.heading, Some((row, col))),
+                        naive(&map, (row, col)),
+                        "{:?}\n{}",
+                        (row, col),
+                        input
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_looping_map() {
+        // INPUT2 already traps the guard without any extra obstacle
+        let map = Map::parse(INPUT2);
+        assert_eq!(p1(INPUT2), 18);
+        assert!(matches!(
+            patrol::simulate(&map, map.start, map.heading, None),
+            Outcome::Loops(Cycle { length: 18, .. })
+        ));
+    }
+
+    #[test]
+    fn test_threads_deterministic() {
+        let map = Map::parse(&random_map(0x0643, 60, 60, 9));
+        let expected = loops(&map, 1);
+        assert!(!expected.is_empty());
+        for threads in [2, 3, 8, 1000] {
+            assert_eq!(loops(&map, threads), expected, "{} threads", threads);
+        }
+    }
+
+    /// cargo test --release -- --ignored --nocapture bench
+    #[test]
+    #[ignore]
+    fn bench_threads() {
+        let map = Map::parse(&random_map(0x0643, 400, 400, 15));
+
+        let run = |threads: usize| {
+            time(&format!("{} threads", threads), || {
+                loops(&map, threads).len()
+            })
+        };
+
+        let single = run(1);
+        for threads in [2, 4, default_threads()] {
+            assert_eq!(run(threads), single);
+        }
+    }
+
+    /// The 10-line diagram following `heading` in the puzzle text at the top of this file.
+    fn diagram(heading: &str) -> String {
+        let source = include_str!("main.rs");
+        let start = source.find(heading).unwrap();
+        source[start..]
+            .lines()
+            .skip(2)
+            .take(10)
+            .map(|line| line.to_string() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn test_render_visited() {
+        let expected = diagram("the positions visited by the guard before leaving the area");
+        assert_eq!(render::visited(&Map::parse(INPUT)), expected);
+    }
+
+    #[test]
+    fn test_render_options() {
+        let map = Map::parse(INPUT);
+        for option in ["one", "two", "three", "four", "five", "six"] {
+            let expected = diagram(&format!("Option {},", option));
+            let obstacle = expected
+                .lines()
+                .enumerate()
+                .find_map(|(row, line)| Some((row, line.find('O')?)))
+                .unwrap();
+            assert_eq!(
+                render::route(&map, Some(obstacle)),
+                expected,
+                "option {}",
+                option
+            );
+        }
+    }
+
+    #[test]
+    fn test_start_headings() {
+        // the example turned a quarter to the right, guard and all
+        let rotated: Vec<String> = (0..10)
+            .map(|col| {
+                INPUT
+                    .lines()
+                    .rev()
+                    .map(|line| match line.as_bytes()[col] {
+                        b'^' => '>',
+                        c => c as char,
+                    })
+                    .collect()
+            })
+            .collect();
+        let rotated = rotated.join("\n");
+        let map = Map::parse(&rotated);
+        assert_eq!((map.start, map.heading), ((4, 3), Heading::Right));
+        assert_eq!(p1(&rotated), 41);
+        assert_eq!(p2(&rotated), 6);
+    }
+
+    #[test]
+    fn test_turn_policies() {
+        // a left-turning guard on the mirrored example walks the mirror image of the original
+        let mirrored: String = INPUT
+            .lines()
+            .map(|line| line.chars().rev().collect::<String>() + "\n")
+            .collect();
+        let patrols = guards::patrol(&Map::parse(&mirrored), TurnPolicy::Left);
+        let right = guards::patrol(&Map::parse(INPUT), TurnPolicy::Right);
+        assert_eq!(patrols.guards[0].visited.len(), 41);
+        assert_eq!(patrols.guards[0].fate, right.guards[0].fate);
+        assert_eq!(p1(INPUT), right.guards[0].visited.len() as i32);
+
+        // bouncing between two obstacles: up 2, turn, down 2, turn
+        let map = Map::parse("#\n.\n^\n.\n#");
+        let guard = &guards::patrol(&map, TurnPolicy::Reverse).guards[0];
+        assert_eq!(guard.visited.len(), 3);
+        assert_eq!(guard.fate, Fate::Loops { tick: 6, period: 6 });
+
+        // right, then left, then right, so the guard climbs the stairs and leaves to the right
+        let map = Map::parse(".#.\n...\n#..\n..#\n^..");
+        let guard = &guards::patrol(&map, TurnPolicy::Alternate).guards[0];
+        assert_eq!(
+            guard.visited,
+            vec![(4, 0), (3, 0), (3, 1), (2, 1), (1, 1), (1, 2)]
+        );
+        assert_eq!(guard.fate, Fate::Leaves { tick: 9 });
+    }
+
+    #[test]
+    fn test_guard_events() {
+        let patrols = guards::patrol(&Map::parse(">...<"), TurnPolicy::Right);
+        assert_eq!(
+            patrols.events,
+            vec![
+                Event::Collision {
+                    tick: 2,
+                    pos: (0, 2),
+                    guards: (0, 1)
+                },
+                Event::Crossing {
+                    tick: 3,
+                    pos: (0, 3),
+                    guard: 0,
+                    other: 1
+                },
+                Event::Crossing {
+                    tick: 3,
+                    pos: (0, 1),
+                    guard: 1,
+                    other: 0
+                },
+            ]
+        );
+
+        // an odd gap means they pass through each other instead, each already on the other's path
+        let patrols = guards::patrol(&Map::parse(">..<"), TurnPolicy::Right);
+        assert!(patrols.events.contains(&Event::Collision {
+            tick: 2,
+            pos: (0, 2),
+            guards: (0, 1)
+        }));
+
+        // both are known to loop by tick 14, but only meet two ticks later
+        let map = Map::parse(
+            ".....#..\n........\n.....v..\n........\n#>.....#\n........\n........\n........\n.....#..",
+        );
+        let patrols = guards::patrol(&map, TurnPolicy::Reverse);
+        assert_eq!(
+            patrols.guards[0].fate,
+            Fate::Loops {
+                tick: 14,
+                period: 14
+            }
+        );
+        assert_eq!(
+            patrols.guards[1].fate,
+            Fate::Loops {
+                tick: 12,
+                period: 12
+            }
+        );
+        assert!(patrols.events.contains(&Event::Collision {
+            tick: 16,
+            pos: (4, 5),
+            guards: (0, 1)
+        }));
+    }
+
+    #[test]
+    fn test_guard_horizon() {
+        // a dozen guards pacing corridors of different lengths, whose loops only all line up
+        // again after far longer than any two of them
+        let corridors: String = (53..65)
+            .map(|len| format!("#>{}#{}\n", ".".repeat(len - 1), ".".repeat(65 - len)))
+            .collect();
+        let patrols = guards::patrol(&Map::parse(&corridors), TurnPolicy::Reverse);
+        assert_eq!(
+            patrols.guards[0].fate,
+            Fate::Loops {
+                tick: 106,
+                period: 106
+            }
+        );
+        assert!(patrols.events.is_empty());
+        assert!(patrols.unresolved.is_empty());
+
+        // two corridors whose loops only line up after more than the horizon
+        let corridors = format!("#>{}#.\n#>{}.#\n", ".".repeat(799), ".".repeat(799));
+        let patrols = guards::patrol(&Map::parse(&corridors), TurnPolicy::Reverse);
+        assert_eq!(patrols.unresolved, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_position() {
+        let map = Map::parse(INPUT);
+        assert_eq!(position("--render", "6,3", &map).unwrap(), (6, 3));
+        assert_eq!(
+            position("--render", "99,99", &map).unwrap_err().to_string(),
+            "--render 99,99 is off the 10x10 map"
+        );
+        assert_eq!(
+            position("--render", "3", &map).unwrap_err().to_string(),
+            "--render takes row,col"
+        );
+        assert_eq!(
+            position("--animate", "10,0", &map).unwrap_err().to_string(),
+            "--animate 10,0 is off the 10x10 map"
+        );
+    }
+
+    #[test]
+    fn test_animation() {
+        let map = Map::parse(INPUT);
+        let mut animation = animate::Animation::new(&map, Some((6, 3)));
+        for _ in 0..6 {
+            animation.step();
+        }
+        // up five cells, then turned to face the wall
+        assert_eq!(
+            (animation.pos, animation.heading, animation.status),
+            ((1, 4), Heading::Right, animate::Status::Walking)
+        );
+        let frame = animation.frame();
+        assert!(frame.starts_with("tick 6      (1, 4) facing Right"));
+        assert!(frame.contains("\x1b[1;31mO\x1b[0m"));
+
+        animation.jump_to_end();
+        assert_eq!(animation.status, animate::Status::Looped);
+        // back to the start facing up, after 18 steps and 4 turns
+        assert_eq!((animation.tick, animation.pos), (22, (6, 4)));
+        assert!(animation.frame().contains("loop detected"));
+
+        let mut animation = animate::Animation::new(&map, None);
+        animation.jump_to_end();
+        assert_eq!(animation.status, animate::Status::Left);
+    }
+
+    /// Play the animation with `commands` already typed, returning what was drawn.
+    fn play(
+        animation: &mut animate::Animation,
+        commands: &[animate::Command],
+        out: &mut impl io::Write,
+    ) -> io::Result<()> {
+        let (send, receive) = mpsc::channel();
+        for &command in commands {
+            send.send(command).unwrap();
+        }
+        drop(send);
+        animate::play(animation, 1000.0, receive, out)
+    }
+
+    #[test]
+    fn test_play() {
+        use animate::{Command, Status};
+        let map = Map::parse(INPUT);
+
+        // step and pause, resume, then skip to where she leaves
+        let mut animation = animate::Animation::new(&map, None);
+        let mut out = Vec::new();
+        let commands = [Command::Step, Command::Pause, Command::JumpToEnd];
+        play(&mut animation, &commands, &mut out).unwrap();
+        assert_eq!(animation.status, Status::Left);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\x1b[?25l"));
+        assert!(out.contains("tick 1      (5, 4) facing Up"));
+        assert!(out.contains("left the map"));
+        assert!(out.ends_with("\x1b[?25h\n"));
+
+        // quitting stops her where she is
+        let mut animation = animate::Animation::new(&map, Some((6, 3)));
+        let mut out = Vec::new();
+        play(&mut animation, &[Command::Step, Command::Quit], &mut out).unwrap();
+        assert_eq!((animation.tick, animation.status), (1, Status::Walking));
+        assert!(String::from_utf8(out).unwrap().ends_with("\x1b[?25h\n"));
+
+        // the cursor comes back even when a frame can't be drawn
+        struct Broken(Vec<u8>, usize);
+        impl io::Write for Broken {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.1 += 1;
+                if self.1 == 3 {
+                    return Err(io::Error::other("terminal went away"));
+                }
+                self.0.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut animation = animate::Animation::new(&map, None);
+        let mut out = Broken(Vec::new(), 0);
+        assert!(play(&mut animation, &[Command::JumpToEnd], &mut out).is_err());
+        assert!(out.0.ends_with(b"\x1b[?25h\n"));
+    }
+}
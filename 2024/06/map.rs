@@ -0,0 +1,168 @@
+//! The lab map, with a table of where the nearest obstacle is from every cell in every heading,
+//! so that the guard can be moved from one turn to the next in a single lookup.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Heading {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Heading {
+    pub const ALL: [Heading; 4] = [Heading::Up, Heading::Right, Heading::Down, Heading::Left];
+
+    pub fn turn_right(self) -> Heading {
+        Heading::ALL[(self.index() + 1) % 4]
+    }
+
+    pub fn turn_left(self) -> Heading {
+        Heading::ALL[(self.index() + 3) % 4]
+    }
+
+    pub fn reverse(self) -> Heading {
+        Heading::ALL[(self.index() + 2) % 4]
+    }
+
+    /// How a guard facing this way is drawn on the map: `^`, `>`, `v` or `<`.
+    pub fn symbol(self) -> u8 {
+        b"^>v<"[self.index()]
+    }
+
+    pub fn from_symbol(c: u8) -> Option<Heading> {
+        Heading::ALL.into_iter().find(|h| h.symbol() == c)
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// `(row, col)` offset of a single step.
+    pub fn delta(self) -> (isize, isize) {
+        match self {
+            Heading::Up => (-1, 0),
+            Heading::Right => (0, 1),
+            Heading::Down => (1, 0),
+            Heading::Left => (0, -1),
+        }
+    }
+}
+
+pub type Pos = (usize, usize);
+
+#[derive(Debug, Clone)]
+pub struct Map {
+    pub height: usize,
+    pub width: usize,
+    obstacles: Vec<bool>,
+    /// Where the first guard on the map starts, and which way she faces.
+    pub start: Pos,
+    pub heading: Heading,
+    /// Every guard on the map, in reading order.
+    pub guards: Vec<(Pos, Heading)>,
+    /// For every cell and heading, the nearest obstacle straight ahead (`NONE` if there is none).
+    ahead: Vec<[usize; 4]>,
+}
+
+const NONE: usize = usize::MAX;
+
+impl Map {
+    pub fn parse(input: &str) -> Self {
+        let lines: Vec<&[u8]> = input.lines().map(|l| l.as_bytes()).collect();
+        let height = lines.len();
+        let width = lines.first().map_or(0, |l| l.len());
+
+        let mut obstacles = vec![false; height * width];
+        let mut guards = Vec::new();
+        for (row, line) in lines.iter().enumerate() {
+            for (col, &c) in line.iter().enumerate() {
+                if c == b'#' {
+                    obstacles[row * width + col] = true;
+                } else if let Some(heading) = Heading::from_symbol(c) {
+                    guards.push(((row, col), heading));
+                }
+            }
+        }
+        let (start, heading) = guards.first().copied().unwrap_or(((0, 0), Heading::Up));
+
+        let mut map = Map {
+            height,
+            width,
+            obstacles,
+            start,
+            heading,
+            guards,
+            ahead: vec![[NONE; 4]; height * width],
+        };
+        map.build_jumps();
+        map
+    }
+
+    fn build_jumps(&mut self) {
+        let (h, w) = (self.height, self.width);
+        // sweep each row and column in both directions, remembering the last obstacle passed
+        for row in 0..h {
+            let mut last = NONE;
+            for col in 0..w {
+                self.ahead[row * w + col][Heading::Left.index()] = last;
+                if self.obstacles[row * w + col] {
+                    last = row * w + col;
+                }
+            }
+            let mut last = NONE;
+            for col in (0..w).rev() {
+                self.ahead[row * w + col][Heading::Right.index()] = last;
+                if self.obstacles[row * w + col] {
+                    last = row * w + col;
+                }
+            }
+        }
+        for col in 0..w {
+            let mut last = NONE;
+            for row in 0..h {
+                self.ahead[row * w + col][Heading::Up.index()] = last;
+                if self.obstacles[row * w + col] {
+                    last = row * w + col;
+                }
+            }
+            let mut last = NONE;
+            for row in (0..h).rev() {
+                self.ahead[row * w + col][Heading::Down.index()] = last;
+                if self.obstacles[row * w + col] {
+                    last = row * w + col;
+                }
+            }
+        }
+    }
+
+    pub fn is_obstacle(&self, pos: Pos) -> bool {
+        self.obstacles[pos.0 * self.width + pos.1]
+    }
+
+    /// The neighbouring cell in `heading`, or `None` at the edge of the map.
+    pub fn step(&self, pos: Pos, heading: Heading) -> Option<Pos> {
+        let (dr, dc) = heading.delta();
+        let row = pos.0.checked_add_signed(dr)?;
+        let col = pos.1.checked_add_signed(dc)?;
+        (row < self.height && col < self.width).then_some((row, col))
+    }
+
+    /// The nearest obstacle straight ahead of `pos`, counting `extra` as an obstacle too.
+    pub fn obstacle_ahead(&self, pos: Pos, heading: Heading, extra: Option<Pos>) -> Option<Pos> {
+        let found = self.ahead[pos.0 * self.width + pos.1][heading.index()];
+        let found = (found != NONE).then(|| (found / self.width, found % self.width));
+
+        let extra = extra.filter(|&o| match heading {
+            Heading::Up => o.1 == pos.1 && o.0 < pos.0,
+            Heading::Down => o.1 == pos.1 && o.0 > pos.0,
+            Heading::Left => o.0 == pos.0 && o.1 < pos.1,
+            Heading::Right => o.0 == pos.0 && o.1 > pos.1,
+        });
+        let distance = |o: Pos| o.0.abs_diff(pos.0) + o.1.abs_diff(pos.1);
+
+        match (found, extra) {
+            (Some(f), Some(e)) => Some(if distance(e) < distance(f) { e } else { f }),
+            (f, e) => f.or(e),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Simulating the guard, with exact loop detection over `(position, heading)` states.
+
+use crate::map::{Heading, Map, Pos};
+
+/// A fixed-size set of small integers.
+#[derive(Debug, Clone)]
+pub struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    pub fn new(len: usize) -> Self {
+        Bitset {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    /// Add `i`, returning whether it was not already present.
+    pub fn insert(&mut self, i: usize) -> bool {
+        let (word, bit) = (i / 64, 1 << (i % 64));
+        let new = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        new
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+}
+
+/// A loop the guard gets stuck in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// The first state of the patrol that is part of the loop.
+    pub entry: (Pos, Heading),
+    /// Steps forward per lap; turning on the spot isn't a step.
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Exits,
+    Loops(Cycle),
+}
+
+fn state(map: &Map, pos: Pos, heading: Heading) -> usize {
+    (pos.0 * map.width + pos.1) * 4 + heading.index()
+}
+
+fn distance(a: Pos, b: Pos) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+/// Follow the guard from `start` with `extra` as an additional obstacle, moving from turn to
+/// turn. She is in a loop as soon as she turns at the same place in the same heading twice.
+pub fn simulate(map: &Map, start: Pos, heading: Heading, extra: Option<Pos>) -> Outcome {
+    let mut seen = Bitset::new(map.height * map.width * 4);
+    // every turn so far, as the position and heading just before turning
+    let mut turns: Vec<(Pos, Heading)> = Vec::new();
+    let (mut pos, mut heading) = (start, heading);
+
+    while let Some(obstacle) = map.obstacle_ahead(pos, heading, extra) {
+        // stop just in front of the obstacle
+        pos = map
+            .step(obstacle, heading.turn_right().turn_right())
+            .unwrap();
+
+        if !seen.insert(state(map, pos, heading)) {
+            let k = turns.iter().position(|&t| t == (pos, heading)).unwrap();
+            let lap = &turns[k..];
+            let length = (0..lap.len())
+                .map(|i| distance(lap[i].0, lap[(i + 1) % lap.len()].0))
+                .sum();
+
+            // The guard reached the turn at `pos` along a straight line, and so does the last
+            // leg of the loop; the loop was entered where those two lines start to overlap.
+            let tail_start = if k == 0 { start } else { turns[k - 1].0 };
+            let lap_start = lap[lap.len() - 1].0;
+            let entry = if distance(tail_start, pos) <= distance(lap_start, pos) {
+                tail_start
+            } else {
+                lap_start
+            };
+
+            return Outcome::Loops(Cycle {
+                entry: (entry, heading),
+                length,
+            });
+        }
+
+        turns.push((pos, heading));
+        heading = heading.turn_right();
+    }
+
+    Outcome::Exits
+}
+
+/// The guard walking step by step, with `extra` as an additional obstacle: every state she is in,
+/// in order, until she either leaves the map or gets back to a state she has been in before.
+pub struct Walk<'a> {
+    map: &'a Map,
+    extra: Option<Pos>,
+    states: Bitset,
+    next: Option<(Pos, Heading)>,
+    /// The state she got back to, once she has.
+    pub repeated: Option<(Pos, Heading)>,
+}
+
+pub fn walk(map: &Map, start: Pos, heading: Heading, extra: Option<Pos>) -> Walk<'_> {
+    Walk {
+        map,
+        extra,
+        states: Bitset::new(map.height * map.width * 4),
+        next: Some((start, heading)),
+        repeated: None,
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = (Pos, Heading);
+
+    fn next(&mut self) -> Option<(Pos, Heading)> {
+        let (pos, heading) = self.next.take()?;
+        if !self.states.insert(state(self.map, pos, heading)) {
+            self.repeated = Some((pos, heading));
+            return None;
+        }
+        self.next = match self.map.step(pos, heading) {
+            None => None,
+            Some(ahead) if self.map.is_obstacle(ahead) || Some(ahead) == self.extra => {
+                Some((pos, heading.turn_right()))
+            }
+            Some(ahead) => Some((ahead, heading)),
+        };
+        Some((pos, heading))
+    }
+}
+
+/// Distinct cells visited by the guard, in the order first visited, until she either leaves the
+/// map or starts repeating herself.
+pub fn path(map: &Map, start: Pos, heading: Heading) -> Vec<Pos> {
+    let mut cells = Bitset::new(map.height * map.width);
+    walk(map, start, heading, None)
+        .map(|(pos, _)| pos)
+        .filter(|pos| cells.insert(pos.0 * map.width + pos.1))
+        .collect()
+}
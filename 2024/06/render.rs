@@ -0,0 +1,56 @@
+//! Drawing the guard's route the way the puzzle does.
+
+use crate::map::{Heading, Map, Pos};
+use crate::patrol;
+
+fn blank(map: &Map) -> Vec<Vec<u8>> {
+    (0..map.height)
+        .map(|row| {
+            (0..map.width)
+                .map(|col| match map.is_obstacle((row, col)) {
+                    true => b'#',
+                    false => b'.',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn join(cells: Vec<Vec<u8>>) -> String {
+    cells
+        .into_iter()
+        .map(|row| String::from_utf8(row).unwrap() + "\n")
+        .collect()
+}
+
+/// The map with every cell the guard visits marked `X`, as in part 1.
+pub fn visited(map: &Map) -> String {
+    let mut cells = blank(map);
+    for ((row, col), _) in patrol::walk(map, map.start, map.heading, None) {
+        cells[row][col] = b'X';
+    }
+    join(cells)
+}
+
+/// The map with the guard's route drawn as in part 2: `|` where she moves up or down, `-` where
+/// she moves left or right, `+` where she does both, `O` for the placed obstacle and the guard's
+/// symbol where she started.
+pub fn route(map: &Map, obstacle: Option<Pos>) -> String {
+    let mut cells = blank(map);
+    for ((row, col), heading) in patrol::walk(map, map.start, map.heading, obstacle) {
+        let vertical = matches!(heading, Heading::Up | Heading::Down);
+        cells[row][col] = match (cells[row][col], vertical) {
+            (b'.', true) => b'|',
+            (b'.', false) => b'-',
+            (b'-', true) | (b'|', false) => b'+',
+            (c, _) => c,
+        };
+    }
+
+    let (row, col) = map.start;
+    cells[row][col] = map.heading.symbol();
+    if let Some((row, col)) = obstacle {
+        cells[row][col] = b'O';
+    }
+    join(cells)
+}
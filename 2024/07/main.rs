@@ -46,60 +46,106 @@ three equations listed above is 3749.
 Determine which equations could possibly be true. What is their total calibration result?
 */
 
-use std::fs;
-use std::io::{self};
-
-// Example recursion:
-// 
-// perms(nums: &[i64], index: usize, current: i64, results: &mut Vec<i64>)
-// 
-// perms([81, 40, 27], 1, 81, [])
-//     perms([81, 40, 27], 2, 81 + 40, [])
-//         perms([81, 40, 27], 3, 81 + 40 + 27, [])
-//             return
-//         perms([81, 40, 27], 3, 81 + 40 * 27, [])
-//             return
-//     perms([81, 40, 27], 2, 81 * 40, [])
-//         perms([81, 40, 27], 3, 81 * 40 + 27, [])
-//             return
-//         perms([81, 40, 27], 3, 81 * 40 * 27, [])
-//             return
-
-pub fn p1(input: &str) -> i64 {
-    fn perms(nums: &[i64], index: usize, current: i64, results: &mut Vec<i64>) {
-        if index == nums.len() {
-            results.push(current);
-            return;
-        }
+/*
 
-        let next_num = nums[index];
+cargo run
+cargo run -- --report
+cargo run -- --report --part 1 --all --json
+cargo run -- --ops +,*,-,/ --precedence
+cargo run -- --report --ops add,multiply,exponent
+cargo run -- --u128
+cargo run -- --u128 --report --all
 
-        //println!("{:?}", (nums, index, current, &results));
-        perms(nums, index + 1, current + next_num, results);
-        perms(nums, index + 1, current * next_num, results);
-    }
+cargo test --release -- --ignored --nocapture bench
 
-    let mut ans = 0;
-    for line in input.lines() {
-        println!("{:?}", line);
-        let (left, right) = line.split_once(':').unwrap();
+*/
 
-        let value = left.parse::<i64>().unwrap();
+mod number;
+mod ops;
+mod report;
+mod solver;
+
+use advent_of_code_common::sections::ParseError;
+use number::Number;
+use ops::{Add, Concat, Mul, Operator, Registry};
+use report::Report;
+use solver::{Evaluation, Solver};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self};
 
-        let nums: Vec<i64> = right
-            .split_whitespace()
-            .filter_map(|s| s.parse().ok())
-            .collect();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    /// The sum of the test values doesn't fit.
+    Overflow,
+}
 
-        let mut results = Vec::new();
-        perms(&nums, 1, nums[0], &mut results);
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
 
-        if results.contains(&value) {
-            ans += value;
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(e) => e.fmt(f),
+            Error::Overflow => write!(f, "total calibration result overflows"),
         }
     }
+}
+
+impl std::error::Error for Error {}
+
+/// Every equation as its test value and numbers, failing on any number too big for an `N`
+/// rather than skipping the line.
+pub fn parse<N: Number>(input: &str) -> Result<Vec<(N, Vec<N>)>, ParseError> {
+    let number = |line: usize, s: &str| {
+        s.parse()
+            .map_err(|_| ParseError::new(line, format!("{:?} is not a number that fits", s)))
+    };
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(i, text)| {
+            let (left, right) = text
+                .split_once(':')
+                .ok_or_else(|| ParseError::new(i + 1, "expected `value: numbers`"))?;
+            let nums = right
+                .split_whitespace()
+                .map(|s| number(i + 1, s))
+                .collect::<Result<Vec<N>, _>>()?;
+            if nums.is_empty() {
+                return Err(ParseError::new(i + 1, "no numbers after the colon"));
+            }
+            Ok((number(i + 1, left.trim())?, nums))
+        })
+        .collect()
+}
+
+/// `+` and `*`, plus `||` for part 2, evaluated left to right.
+pub fn solver<N: Number>(concat: bool) -> Solver<'static, N> {
+    let mut operators: Vec<&dyn Operator<N>> = vec![&Add, &Mul];
+    if concat {
+        operators.push(&Concat);
+    }
+    Solver::new(operators, Evaluation::LeftToRight)
+}
 
-    ans
+/// Sum of the test values of the equations that can be made true.
+pub fn calibration<N: Number>(input: &str, solver: &Solver<N>) -> Result<N, Error> {
+    parse(input)?
+        .into_iter()
+        .filter(|(value, nums)| solver.solvable(*value, nums))
+        .try_fold(N::ZERO, |total, (value, _)| total.checked_add(value))
+        .ok_or(Error::Overflow)
+}
+
+pub fn p1(input: &str) -> Result<i64, Error> {
+    calibration(input, &solver(false))
 }
 
 /*
@@ -126,8 +172,134 @@ Using your new knowledge of elephant hiding spots, determine which equations cou
 true. What is their total calibration result?
 */
 
-pub fn p2(input: &str) -> i64 {
-    fn perms(nums: &[i64], index: usize, current: i64, results: &mut Vec<i64>) {
+pub fn p2(input: &str) -> Result<i64, Error> {
+    calibration(input, &solver(true))
+}
+
+pub static INPUT: &str = "\
+190: 10 19
+3267: 81 40 27
+83: 17 5
+156: 15 6
+7290: 6 8 6 15
+161011: 16 10 13
+192: 17 8 14
+21037: 9 7 18 13
+292: 11 6 16 20";
+
+pub fn reports<N: Number>(
+    input: &str,
+    solver: &Solver<N>,
+    all: bool,
+) -> Result<Vec<Report<N>>, ParseError> {
+    Ok(parse(input)?
+        .iter()
+        .map(|(value, nums)| Report::new(solver, *value, nums, all))
+        .collect())
+}
+
+fn invalid(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Everything but the plain answers, in whichever number type was asked for.
+fn run<N: Number>(input: &str, args: &[String]) -> io::Result<()> {
+    let flag = |name: &str| args.iter().position(|a| a == name).map(|i| args.get(i + 1));
+
+    let registry = Registry::default();
+    let evaluation = match args.iter().any(|a| a == "--precedence") {
+        true => Evaluation::Precedence,
+        false => Evaluation::LeftToRight,
+    };
+    let solver = match flag("--ops") {
+        Some(list) => {
+            let list = list.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--ops takes a list like +,*,||",
+                )
+            })?;
+            let operators = registry
+                .select(list)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            Solver::new(operators, evaluation)
+        }
+        None => Solver::new(solver::<N>(true).operators, evaluation),
+    };
+
+    if args.iter().any(|a| a == "--report") {
+        // part 2's operators unless asked for part 1's or some other choice
+        let part1 = flag("--part").is_some_and(|p| p.is_some_and(|p| p == "1"));
+        let solver = match part1 && flag("--ops").is_none() {
+            true => Solver::new(self::solver::<N>(false).operators, evaluation),
+            false => solver,
+        };
+        let reports =
+            reports(input, &solver, args.iter().any(|a| a == "--all")).map_err(invalid)?;
+        if args.iter().any(|a| a == "--json") {
+            print!("{}", report::to_json(&reports));
+        } else {
+            reports.iter().for_each(|r| print!("{}", r.to_text()));
+        }
+        return Ok(());
+    }
+
+    println!("total {}", calibration(input, &solver).map_err(invalid)?);
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let input = fs::read_to_string("input.txt")?;
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let is = |name: &str| args.iter().any(|a| a == name);
+    if is("--u128") {
+        return match is("--report") || is("--ops") || is("--precedence") {
+            true => run::<u128>(&input, &args),
+            false => {
+                println!(
+                    "p1 {}",
+                    calibration::<u128>(&input, &solver(false)).map_err(invalid)?
+                );
+                println!(
+                    "p2 {}",
+                    calibration::<u128>(&input, &solver(true)).map_err(invalid)?
+                );
+                Ok(())
+            }
+        };
+    }
+    if is("--report") || is("--ops") || is("--precedence") {
+        return run::<i64>(&input, &args);
+    }
+
+    println!("p1 {}", p1(&input).map_err(invalid)?);
+    println!("p2 {}", p2(&input).map_err(invalid)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_common::testing::{time, XorShift};
+    use ops::{Div, Exp, Undo};
+
+    // The original enumeration, kept as a reference for the reverse search.
+    //
+    // perms(nums: &[i64], index: usize, current: i64, results: &mut Vec<i64>)
+    //
+    // perms([81, 40, 27], 1, 81, [])
+    //     perms([81, 40, 27], 2, 81 + 40, [])
+    //         perms([81, 40, 27], 3, 81 + 40 + 27, [])
+    //             return
+    //         perms([81, 40, 27], 3, 81 + 40 * 27, [])
+    //             return
+    //     perms([81, 40, 27], 2, 81 * 40, [])
+    //         perms([81, 40, 27], 3, 81 * 40 + 27, [])
+    //             return
+    //         perms([81, 40, 27], 3, 81 * 40 * 27, [])
+    //             return
+    fn perms(nums: &[i64], index: usize, current: i64, concat: bool, results: &mut Vec<i64>) {
         if index == nums.len() {
             results.push(current);
             return;
@@ -135,64 +307,320 @@ pub fn p2(input: &str) -> i64 {
 
         let next_num = nums[index];
 
-        perms(nums, index + 1, current + next_num, results);
-        perms(nums, index + 1, current * next_num, results);
-        perms(
-            nums,
-            index + 1,
-            format!("{}{}", current, next_num).parse::<i64>().unwrap(),
-            results,
-        );
+        perms(nums, index + 1, current + next_num, concat, results);
+        perms(nums, index + 1, current * next_num, concat, results);
+        if concat {
+            perms(
+                nums,
+                index + 1,
+                format!("{}{}", current, next_num).parse::<i64>().unwrap(),
+                concat,
+                results,
+            );
+        }
     }
 
-    let mut ans = 0;
-    for line in input.lines() {
-        println!("{:?}", line);
-        let (left, right) = line.split_once(':').unwrap();
+    fn enumerate(input: &str, concat: bool) -> i64 {
+        let mut ans = 0;
+        for (value, nums) in parse::<i64>(input).unwrap() {
+            let mut results = Vec::new();
+            perms(&nums, 1, nums[0], concat, &mut results);
+            if results.contains(&value) {
+                ans += value;
+            }
+        }
+        ans
+    }
+
+    /// `count` equations of `len` numbers from 1 to `max`; about half are made true with a random
+    /// choice of operators, the rest are off by one.
+    fn long_equations(count: usize, len: usize, max: u64, concat: bool) -> String {
+        let mut rng = XorShift::new(0x0707);
+        let mut next = |n: u64| rng.below(n);
+
+        let mut input = String::new();
+        for _ in 0..count {
+            let nums: Vec<i64> = (0..len).map(|_| 1 + next(max) as i64).collect();
+            let mut value = nums[0];
+            for &n in &nums[1..] {
+                value = match next(if concat { 3 } else { 2 }) {
+                    0 => value + n,
+                    1 if value.checked_mul(n).is_some_and(|v| v < i64::MAX / 1000) => value * n,
+                    2 if value < i64::MAX / 1000 => format!("{}{}", value, n).parse().unwrap(),
+                    _ => value + n,
+                };
+            }
+            value += next(2) as i64;
+            let nums: Vec<String> = nums.iter().map(|n| n.to_string()).collect();
+            input += &format!("{}: {}\n", value, nums.join(" "));
+        }
+        input
+    }
 
-        let value = left.parse::<i64>().unwrap();
+    #[test]
+    fn test_p1p2() {
+        assert_eq!(p1(INPUT), Ok(3749));
+        assert_eq!(p2(INPUT), Ok(11387));
+    }
 
-        let nums: Vec<i64> = right
-            .split_whitespace()
-            .filter_map(|s| s.parse().ok())
+    #[test]
+    fn test_matches_enumeration() {
+        for concat in [false, true] {
+            let input = long_equations(200, 7, 99, concat);
+            assert_eq!(
+                calibration(&input, &solver::<i64>(concat)),
+                Ok(enumerate(&input, concat))
+            );
+        }
+    }
+
+    #[test]
+    fn test_zeros() {
+        assert!(solver::<i64>(false).solvable(0, &[5, 3, 0]));
+        // whatever comes before a final `* 0`
+        assert_eq!(solver::<i64>(false).solutions(0, &[5, 3, 0]).len(), 2);
+        assert!(solver::<i64>(false).solvable(7, &[5, 0, 7]));
+        assert!(solver::<i64>(true).solvable(50, &[5, 0]));
+        assert!(!solver::<i64>(true).solvable(6, &[5, 0]));
+    }
+
+    /// cargo test --release -- --ignored --nocapture bench
+    #[test]
+    #[ignore]
+    fn bench_reverse_vs_enumeration() {
+        for concat in [false, true] {
+            // single digits keep every combination the enumeration tries within an i64
+            let input = long_equations(100, if concat { 12 } else { 16 }, 9, concat);
+            assert_eq!(
+                time(&format!("enumerate concat={}", concat), || {
+                    enumerate(&input, concat)
+                }),
+                time(&format!("reverse concat={}", concat), || {
+                    calibration(&input, &solver::<i64>(concat)).unwrap()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_reports() {
+        let part1 = reports(INPUT, &solver::<i64>(false), true).unwrap();
+        let solved: Vec<(i64, usize)> = part1
+            .iter()
+            .filter(|r| r.count > 0)
+            .map(|r| (r.value, r.count))
             .collect();
+        assert_eq!(solved, vec![(190, 1), (3267, 2), (292, 1)]);
+        assert_eq!(
+            part1[1].to_text(),
+            "3267: 81 40 27 2 solutions\n  3267 = 81 * 40 + 27\n  3267 = 81 + 40 * 27\n"
+        );
+        assert_eq!(
+            part1[8].to_json(),
+            "{\"value\": 292, \"numbers\": [11, 6, 16, 20], \"count\": 1, \"solutions\": [\"11 + 6 * 16 + 20\"]}"
+        );
+        assert_eq!(part1[2].to_text(), "83: 17 5 no solution\n");
 
-        let mut results = Vec::new();
-        perms(&nums, 1, nums[0], &mut results);
+        let part2 = reports(INPUT, &solver::<i64>(true), false).unwrap();
+        assert_eq!(
+            part2[3].to_text(),
+            "156: 15 6 1 solution\n  156 = 15 || 6\n"
+        );
+        assert_eq!(part2[4].solutions, vec!["6 * 8 || 6 * 15"]);
+        assert_eq!(
+            part2[6].to_text(),
+            "192: 17 8 14 1 solution\n  192 = 17 || 8 + 14\n"
+        );
+    }
 
-        if results.contains(&value) {
-            ans += value;
+    #[test]
+    fn test_solutions_match_enumeration() {
+        // every listed solution evaluates to the test value, and the count matches a brute force
+        for concat in [false, true] {
+            let solver = solver::<i64>(concat);
+            for (value, nums) in parse::<i64>(&long_equations(200, 6, 9, concat)).unwrap() {
+                let mut results = Vec::new();
+                perms(&nums, 1, nums[0], concat, &mut results);
+                let solutions = solver.solutions(value, &nums);
+                assert_eq!(
+                    solutions.len(),
+                    results.iter().filter(|&&r| r == value).count()
+                );
+                for ops in solutions {
+                    assert_eq!(solver.evaluate(&nums, &ops), Some(value));
+                }
+            }
         }
     }
 
-    ans
-}
+    /// Every solution, by evaluating every combination of operators.
+    fn brute_force(solver: &Solver<i64>, value: i64, nums: &[i64]) -> Vec<solver::Solution> {
+        let k = solver.operators.len();
+        let gaps = nums.len() - 1;
+        let mut all = (0..k.pow(gaps as u32))
+            .map(|mut n| {
+                (0..gaps)
+                    .map(|_| {
+                        let op = n % k;
+                        n /= k;
+                        op
+                    })
+                    .collect::<Vec<usize>>()
+            })
+            .filter(|ops| solver.evaluate(nums, ops) == Some(value))
+            .collect::<Vec<_>>();
+        all.sort();
+        all
+    }
 
-pub static INPUT: &str = "\
-190: 10 19
-3267: 81 40 27
-83: 17 5
-156: 15 6
-7290: 6 8 6 15
-161011: 16 10 13
-192: 17 8 14
-21037: 9 7 18 13
-292: 11 6 16 20";
+    #[test]
+    fn test_operator_registry() {
+        let registry = Registry::<i64>::default();
+        let operators = registry.select("+, multiply,||,-").unwrap();
+        let symbols: Vec<&str> = operators.iter().map(|o| o.symbol()).collect();
+        assert_eq!(symbols, vec!["+", "*", "||", "-"]);
+        assert_eq!(
+            registry.select("+,%").err().unwrap().to_string(),
+            "unknown operator \"%\""
+        );
+        // the same operator twice would count each of its solutions twice
+        assert_eq!(
+            registry.select("+,*,+").err().unwrap().to_string(),
+            "operator \"+\" is already in the list"
+        );
+        assert_eq!(
+            registry.select("*,multiply").err(),
+            Some(ops::SelectError::Repeated("multiply".to_string()))
+        );
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // subtraction takes values below zero, so the search can't give up on them
+        let solver = Solver::new(registry.select("+,-").unwrap(), Evaluation::LeftToRight);
+        assert_eq!(solver.solutions(-1, &[2, 5, 2]).len(), 1);
+        assert_eq!(solver.solutions(5, &[2, 5, 2]).len(), 1);
+        assert_eq!(
+            solver.expression(&[2, 5, 2], &solver.solve(-1, &[2, 5, 2]).unwrap()),
+            "2 - 5 + 2"
+        );
+
+        // integer division and exponents can't always be undone, so they are tried forwards
+        let solver = Solver::<i64>::new(vec![&Add, &Div, &Exp], Evaluation::LeftToRight);
+        assert_eq!(
+            solver.expression(&[10, 3, 2], &solver.solve(6, &[10, 3, 2]).unwrap()),
+            "10 + 3 / 2"
+        );
+        assert_eq!(
+            solver.expression(&[2, 3, 2], &solver.solve(64, &[2, 3, 2]).unwrap()),
+            "2 ^ 3 ^ 2"
+        );
+    }
 
     #[test]
-    fn test_p1p2() {
-        assert_eq!(p1(&INPUT), 3749);
-        assert_eq!(p2(&INPUT), 11387);
+    fn test_all_operators_match_brute_force() {
+        let registry = Registry::<i64>::default();
+        let all = registry.select("+,*,||,-,/,^").unwrap();
+        let mut rng = XorShift::new(0x0749);
+        let mut next = |n: u64| rng.below(n) as i64;
+
+        for evaluation in [Evaluation::LeftToRight, Evaluation::Precedence] {
+            for _ in 0..1000 {
+                // a random subset of at least one operator
+                let mask = 1 + next(63);
+                let operators = (0..6)
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| all[i])
+                    .collect();
+                let solver = Solver::new(operators, evaluation);
+                let nums: Vec<i64> = (0..1 + next(5)).map(|_| next(12)).collect();
+                let value = next(40) - 5;
+
+                let mut solutions = solver.solutions(value, &nums);
+                solutions.sort();
+                assert_eq!(solutions, brute_force(&solver, value, &nums), "{:?}", nums);
+            }
+        }
     }
-}
 
-fn main() -> io::Result<()> {
-    let input = fs::read_to_string("input.txt")?;
-    println!("p1 {}", p1(&input));
-    println!("p2 {}", p2(&input));
-    Ok(())
+    #[test]
+    fn test_precedence() {
+        let solver = Solver::new(solver::<i64>(true).operators, Evaluation::Precedence);
+        assert_eq!(solver.evaluate(&[81, 40, 27], &[0, 1]), Some(1161));
+        // concatenation binds loosest: (1 + 2) || (3 * 4)
+        assert_eq!(solver.evaluate(&[1, 2, 3, 4], &[0, 2, 1]), Some(312));
+        let reports = reports(INPUT, &solver, true).unwrap();
+        assert_eq!(reports[1].solutions, vec!["81 * 40 + 27"]);
+        assert_eq!(reports[8].count, 0);
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(Concat.apply(i64::MAX, 1), None);
+        assert_eq!(Concat.apply(922337203685477580_i64, 7), Some(i64::MAX));
+        assert_eq!(
+            Operator::<i64>::undo(&Concat, 7, 123_456_789_012_345_678),
+            Undo::Impossible
+        );
+
+        // evaluated forwards, 4e9 * 4e9 overflows and is dropped instead of wrapping or panicking
+        let forward = Solver::<i64>::new(solver(false).operators, Evaluation::Precedence);
+        let nums = [4_000_000_000, 4_000_000_000, 3];
+        assert_eq!(forward.solutions(8_000_000_003, &nums), vec![vec![0, 0]]);
+        assert_eq!(
+            Solver::<u128>::new(solver(false).operators, Evaluation::Precedence).solutions(
+                16_000_000_000_000_000_003,
+                &[4_000_000_000, 4_000_000_000, 3]
+            ),
+            vec![vec![1, 0]]
+        );
+    }
+
+    #[test]
+    fn test_u128_beyond_i64() {
+        // 2^62 * 4 = 2^64 on the way to 2^61
+        let narrow = Registry::<i64>::default();
+        let narrow = Solver::new(narrow.select("+,*,/").unwrap(), Evaluation::LeftToRight);
+        assert!(!narrow.solvable(1 << 61, &[1 << 62, 4, 8]));
+        let wide = Registry::<u128>::default();
+        let wide = Solver::new(wide.select("+,*,/").unwrap(), Evaluation::LeftToRight);
+        let nums = [1 << 62, 4, 8];
+        assert_eq!(
+            wide.expression(&nums, &wide.solve(1 << 61, &nums).unwrap()),
+            "4611686018427387904 * 4 / 8"
+        );
+
+        // test values that don't fit in an i64 at all are an error rather than skipped
+        let input = "190: 10 19\n92233720368547758071: 9223372036854775807 1\n";
+        assert_eq!(
+            calibration::<i64>(input, &solver::<i64>(true)),
+            Err(Error::Parse(ParseError::new(
+                2,
+                "\"92233720368547758071\" is not a number that fits"
+            )))
+        );
+        assert_eq!(
+            calibration::<u128>(input, &solver(true)),
+            Ok(92233720368547758071 + 190)
+        );
+
+        let input = "9223372036854775807: 9223372036854775807\n1: 1\n";
+        assert_eq!(
+            calibration::<i64>(input, &solver(false)),
+            Err(Error::Overflow)
+        );
+        assert_eq!(
+            calibration::<u128>(input, &solver(false)),
+            Ok(9223372036854775808)
+        );
+    }
+
+    #[test]
+    fn test_u128_matches_i64() {
+        for concat in [false, true] {
+            let input = long_equations(200, 7, 99, concat);
+            assert_eq!(
+                calibration::<u128>(&input, &solver(concat)),
+                calibration::<i64>(&input, &solver(concat)).map(|total| total as u128)
+            );
+        }
+        assert_eq!(calibration::<u128>(INPUT, &solver(true)), Ok(11387));
+    }
 }
@@ -0,0 +1,63 @@
+//! The integer types equations can be solved in. Every operation is checked, so a branch that
+//! would overflow is dropped instead of wrapping or panicking.
+
+use std::fmt;
+use std::str::FromStr;
+
+pub trait Number: Copy + Ord + fmt::Debug + fmt::Display + FromStr + Send + Sync + 'static {
+    const ZERO: Self;
+    const ONE: Self;
+    const TEN: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_div(self, other: Self) -> Option<Self>;
+    fn checked_rem(self, other: Self) -> Option<Self>;
+    fn checked_pow(self, exp: u32) -> Option<Self>;
+    fn to_u32(self) -> Option<u32>;
+}
+
+macro_rules! number {
+    ($($t:ty),*) => {
+        $(impl Number for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const TEN: Self = 10;
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+            fn checked_div(self, other: Self) -> Option<Self> {
+                <$t>::checked_div(self, other)
+            }
+            fn checked_rem(self, other: Self) -> Option<Self> {
+                <$t>::checked_rem(self, other)
+            }
+            fn checked_pow(self, exp: u32) -> Option<Self> {
+                <$t>::checked_pow(self, exp)
+            }
+            fn to_u32(self) -> Option<u32> {
+                u32::try_from(self).ok()
+            }
+        })*
+    };
+}
+
+number!(i64, u128);
+
+/// `10` to the power of the number of decimal digits in `n`, so that concatenating `n` onto `a`
+/// is `a * magnitude(n) + n`. `None` if that power doesn't fit.
+pub fn magnitude<N: Number>(n: N) -> Option<N> {
+    let mut pow = N::TEN;
+    while pow <= n {
+        pow = pow.checked_mul(N::TEN)?;
+    }
+    Some(pow)
+}
@@ -0,0 +1,260 @@
+//! The operators the elephants might be holding, and a registry to pick them from by name.
+
+use crate::number::{magnitude, Number};
+use std::error;
+use std::fmt;
+
+/// What the left operand must have been for `a op b` to give a known result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Undo<N> {
+    Impossible,
+    Exactly(N),
+    /// More than one value might work, as with `a * 0 = 0`; the solver has to try them forwards
+    /// instead.
+    Unknown,
+}
+
+pub trait Operator<N: Number>: Sync {
+    fn symbol(&self) -> &str;
+    fn name(&self) -> &str;
+    /// `a op b`, or `None` if it is undefined or doesn't fit in an `N`.
+    fn apply(&self, a: N, b: N) -> Option<N>;
+    /// Binding strength under standard precedence; higher binds tighter.
+    fn precedence(&self) -> u8;
+
+    fn undo(&self, _result: N, _b: N) -> Undo<N> {
+        Undo::Unknown
+    }
+
+    fn right_associative(&self) -> bool {
+        false
+    }
+
+    /// Whether non-negative operands always give a non-negative result.
+    fn keeps_non_negative(&self) -> bool {
+        true
+    }
+}
+
+pub struct Add;
+pub struct Mul;
+pub struct Concat;
+pub struct Sub;
+pub struct Div;
+pub struct Exp;
+
+impl<N: Number> Operator<N> for Add {
+    fn symbol(&self) -> &str {
+        "+"
+    }
+    fn name(&self) -> &str {
+        "add"
+    }
+    fn apply(&self, a: N, b: N) -> Option<N> {
+        a.checked_add(b)
+    }
+    fn precedence(&self) -> u8 {
+        1
+    }
+    fn undo(&self, result: N, b: N) -> Undo<N> {
+        result
+            .checked_sub(b)
+            .map_or(Undo::Impossible, Undo::Exactly)
+    }
+}
+
+impl<N: Number> Operator<N> for Mul {
+    fn symbol(&self) -> &str {
+        "*"
+    }
+    fn name(&self) -> &str {
+        "multiply"
+    }
+    fn apply(&self, a: N, b: N) -> Option<N> {
+        a.checked_mul(b)
+    }
+    fn precedence(&self) -> u8 {
+        2
+    }
+    fn undo(&self, result: N, b: N) -> Undo<N> {
+        if b == N::ZERO {
+            return match result == N::ZERO {
+                true => Undo::Unknown,
+                false => Undo::Impossible,
+            };
+        }
+        match result.checked_rem(b) {
+            Some(r) if r == N::ZERO => result
+                .checked_div(b)
+                .map_or(Undo::Impossible, Undo::Exactly),
+            _ => Undo::Impossible,
+        }
+    }
+}
+
+/// Joins the digits of two non-negative numbers: `12 || 345 = 12345`.
+impl<N: Number> Operator<N> for Concat {
+    fn symbol(&self) -> &str {
+        "||"
+    }
+    fn name(&self) -> &str {
+        "concatenate"
+    }
+    fn apply(&self, a: N, b: N) -> Option<N> {
+        if a < N::ZERO || b < N::ZERO {
+            return None;
+        }
+        a.checked_mul(magnitude(b)?)?.checked_add(b)
+    }
+    /// Lowest of all, so that it joins whole sub-expressions: `1 + 2 || 3 = 33`.
+    fn precedence(&self) -> u8 {
+        0
+    }
+    fn undo(&self, result: N, b: N) -> Undo<N> {
+        if result < N::ZERO || b < N::ZERO {
+            return Undo::Impossible;
+        }
+        // a `b` with more digits than fit in an `N` can't be the tail of `result`
+        match magnitude(b) {
+            Some(pow) if result.checked_rem(pow) == Some(b) => result
+                .checked_div(pow)
+                .map_or(Undo::Impossible, Undo::Exactly),
+            _ => Undo::Impossible,
+        }
+    }
+}
+
+impl<N: Number> Operator<N> for Sub {
+    fn symbol(&self) -> &str {
+        "-"
+    }
+    fn name(&self) -> &str {
+        "subtract"
+    }
+    fn apply(&self, a: N, b: N) -> Option<N> {
+        a.checked_sub(b)
+    }
+    fn precedence(&self) -> u8 {
+        1
+    }
+    fn undo(&self, result: N, b: N) -> Undo<N> {
+        result
+            .checked_add(b)
+            .map_or(Undo::Impossible, Undo::Exactly)
+    }
+    fn keeps_non_negative(&self) -> bool {
+        false
+    }
+}
+
+/// Integer division, rounding towards zero; several left operands give the same quotient.
+impl<N: Number> Operator<N> for Div {
+    fn symbol(&self) -> &str {
+        "/"
+    }
+    fn name(&self) -> &str {
+        "divide"
+    }
+    fn apply(&self, a: N, b: N) -> Option<N> {
+        a.checked_div(b)
+    }
+    fn precedence(&self) -> u8 {
+        2
+    }
+}
+
+impl<N: Number> Operator<N> for Exp {
+    fn symbol(&self) -> &str {
+        "^"
+    }
+    fn name(&self) -> &str {
+        "exponent"
+    }
+    fn apply(&self, a: N, b: N) -> Option<N> {
+        a.checked_pow(b.to_u32()?)
+    }
+    fn precedence(&self) -> u8 {
+        3
+    }
+    fn undo(&self, result: N, b: N) -> Undo<N> {
+        if b == N::ZERO && result != N::ONE {
+            Undo::Impossible
+        } else if b == N::ONE {
+            Undo::Exactly(result)
+        } else {
+            Undo::Unknown
+        }
+    }
+    fn right_associative(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectError {
+    Unknown(String),
+    /// Naming an operator twice, even by symbol and then by name, would count every solution
+    /// using it twice.
+    Repeated(String),
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectError::Unknown(key) => write!(f, "unknown operator {:?}", key),
+            SelectError::Repeated(key) => {
+                write!(f, "operator {:?} is already in the list", key)
+            }
+        }
+    }
+}
+
+impl error::Error for SelectError {}
+
+pub struct Registry<N: Number> {
+    operators: Vec<Box<dyn Operator<N>>>,
+}
+
+impl<N: Number> Default for Registry<N> {
+    /// Every operator defined here.
+    fn default() -> Self {
+        Registry::new()
+            .register(Add)
+            .register(Mul)
+            .register(Concat)
+            .register(Sub)
+            .register(Div)
+            .register(Exp)
+    }
+}
+
+impl<N: Number> Registry<N> {
+    pub fn new() -> Self {
+        Registry {
+            operators: Vec::new(),
+        }
+    }
+
+    pub fn register(mut self, operator: impl Operator<N> + 'static) -> Self {
+        self.operators.push(Box::new(operator));
+        self
+    }
+
+    /// The operators named (by symbol or name) in a comma-separated list such as `+,*,||`, each
+    /// at most once.
+    pub fn select(&self, list: &str) -> Result<Vec<&dyn Operator<N>>, SelectError> {
+        let mut selected: Vec<&dyn Operator<N>> = Vec::new();
+        for key in list.split(',').map(str::trim) {
+            let operator = self
+                .operators
+                .iter()
+                .find(|o| o.symbol() == key || o.name() == key)
+                .ok_or_else(|| SelectError::Unknown(key.to_string()))?;
+            if selected.iter().any(|o| o.symbol() == operator.symbol()) {
+                return Err(SelectError::Repeated(key.to_string()));
+            }
+            selected.push(operator.as_ref());
+        }
+        Ok(selected)
+    }
+}
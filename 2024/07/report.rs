@@ -0,0 +1,75 @@
+//! Showing which operators make each equation true.
+
+use crate::number::Number;
+use crate::solver::Solver;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report<N> {
+    pub value: N,
+    pub nums: Vec<N>,
+    /// Number of distinct operator combinations that make the equation true.
+    pub count: usize,
+    /// Every solution written out when listing them all, otherwise just the first.
+    pub solutions: Vec<String>,
+}
+
+impl<N: Number> Report<N> {
+    pub fn new(solver: &Solver<N>, value: N, nums: &[N], all: bool) -> Self {
+        let mut solutions = solver.solutions(value, nums);
+        let count = solutions.len();
+        if !all {
+            solutions.truncate(1);
+        }
+        Report {
+            value,
+            nums: nums.to_vec(),
+            count,
+            solutions: solutions
+                .iter()
+                .map(|ops| solver.expression(nums, ops))
+                .collect(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let nums: Vec<String> = self.nums.iter().map(|n| n.to_string()).collect();
+        let mut out = format!("{}: {}", self.value, nums.join(" "));
+        match self.count {
+            0 => out.push_str(" no solution\n"),
+            1 => out.push_str(" 1 solution\n"),
+            n => out.push_str(&format!(" {} solutions\n", n)),
+        }
+        for expression in &self.solutions {
+            out.push_str(&format!("  {} = {}\n", self.value, expression));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let expressions: Vec<String> = self
+            .solutions
+            .iter()
+            .map(|e| format!("\"{}\"", e))
+            .collect();
+        format!(
+            "{{\"value\": {}, \"numbers\": [{}], \"count\": {}, \"solutions\": [{}]}}",
+            self.value,
+            self.nums
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.count,
+            expressions.join(", ")
+        )
+    }
+}
+
+/// All reports as a JSON array, one equation per line.
+pub fn to_json<N: Number>(reports: &[Report<N>]) -> String {
+    let lines: Vec<String> = reports
+        .iter()
+        .map(|r| format!("  {}", r.to_json()))
+        .collect();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
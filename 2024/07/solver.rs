@@ -0,0 +1,217 @@
+//! Finding operators that make an equation true.
+//!
+//! Evaluated strictly left to right, the search works backwards from the test value: the last
+//! operator applied must undo cleanly against the last number, so most branches die after a
+//! single check instead of being evaluated out. Operators that can't be undone to a single value
+//! fall back to trying every way of evaluating what comes before them. Under standard precedence
+//! every combination is evaluated.
+
+use crate::number::Number;
+use crate::ops::{Operator, Undo};
+use std::ops::ControlFlow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evaluation {
+    LeftToRight,
+    Precedence,
+}
+
+/// A choice of operator for each gap in an equation, as indices into the solver's operators.
+pub type Solution = Vec<usize>;
+
+type Found<'f> = dyn FnMut(&[usize]) -> ControlFlow<()> + 'f;
+
+pub struct Solver<'a, N: Number> {
+    pub operators: Vec<&'a dyn Operator<N>>,
+    pub evaluation: Evaluation,
+}
+
+impl<'a, N: Number> Solver<'a, N> {
+    pub fn new(operators: Vec<&'a dyn Operator<N>>, evaluation: Evaluation) -> Self {
+        Solver {
+            operators,
+            evaluation,
+        }
+    }
+
+    /// `nums` with the operators in `solution` between them, e.g. `81 + 40 * 27`.
+    pub fn expression(&self, nums: &[N], solution: &[usize]) -> String {
+        let mut out = nums[0].to_string();
+        for (&op, n) in solution.iter().zip(&nums[1..]) {
+            out += &format!(" {} {}", self.operators[op].symbol(), n);
+        }
+        out
+    }
+
+    /// The value of `expression(nums, ops)`, or `None` if some step is undefined or overflows.
+    pub fn evaluate(&self, nums: &[N], ops: &[usize]) -> Option<N> {
+        match self.evaluation {
+            Evaluation::LeftToRight => ops
+                .iter()
+                .zip(&nums[1..])
+                .try_fold(nums[0], |acc, (&op, &n)| self.operators[op].apply(acc, n)),
+            Evaluation::Precedence => {
+                let mut values = vec![nums[0]];
+                let mut pending: Vec<&dyn Operator<N>> = Vec::new();
+                let reduce = |values: &mut Vec<N>, op: &dyn Operator<N>| {
+                    let b = values.pop()?;
+                    let a = values.pop()?;
+                    values.push(op.apply(a, b)?);
+                    Some(())
+                };
+                for (&op, &n) in ops.iter().zip(&nums[1..]) {
+                    let op = self.operators[op];
+                    while let Some(&top) = pending.last() {
+                        let binds = top.precedence() > op.precedence()
+                            || (top.precedence() == op.precedence() && !op.right_associative());
+                        if !binds {
+                            break;
+                        }
+                        pending.pop();
+                        reduce(&mut values, top)?;
+                    }
+                    pending.push(op);
+                    values.push(n);
+                }
+                while let Some(top) = pending.pop() {
+                    reduce(&mut values, top)?;
+                }
+                values.pop()
+            }
+        }
+    }
+
+    /// Every way of filling the gaps in `nums`, left to right, with the running value; a branch
+    /// stops as soon as it is undefined or overflows.
+    fn forward(
+        &self,
+        nums: &[N],
+        value: N,
+        ops: &mut Vec<usize>,
+        found: &mut dyn FnMut(N, &[usize]) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let Some((&next, rest)) = nums.split_first() else {
+            return found(value, ops);
+        };
+        for (i, op) in self.operators.iter().enumerate() {
+            if let Some(value) = op.apply(value, next) {
+                ops.push(i);
+                let flow = self.forward(rest, value, ops, found);
+                ops.pop();
+                flow?;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Call `found` with every solution making `target` from `nums` when evaluated left to right,
+    /// working backwards from the last number. `suffix` holds the operators already chosen, from
+    /// the right.
+    fn backward(
+        &self,
+        target: N,
+        nums: &[N],
+        non_negative: bool,
+        suffix: &mut Vec<usize>,
+        found: &mut Found,
+    ) -> ControlFlow<()> {
+        let Some((&last, rest)) = nums.split_last() else {
+            return ControlFlow::Continue(());
+        };
+        if rest.is_empty() {
+            if target == last {
+                let ops: Vec<usize> = suffix.iter().rev().copied().collect();
+                found(&ops)?;
+            }
+            return ControlFlow::Continue(());
+        }
+
+        for (i, op) in self.operators.iter().enumerate() {
+            suffix.push(i);
+            let flow = match op.undo(target, last) {
+                Undo::Impossible => ControlFlow::Continue(()),
+                // nothing made of non-negative parts comes out negative
+                Undo::Exactly(before) if non_negative && before < N::ZERO => {
+                    ControlFlow::Continue(())
+                }
+                Undo::Exactly(before) => self.backward(before, rest, non_negative, suffix, found),
+                Undo::Unknown => self.forward(
+                    &rest[1..],
+                    rest[0],
+                    &mut Vec::new(),
+                    &mut |value, prefix| {
+                        if op.apply(value, last) != Some(target) {
+                            return ControlFlow::Continue(());
+                        }
+                        let ops: Vec<usize> =
+                            prefix.iter().chain(suffix.iter().rev()).copied().collect();
+                        found(&ops)
+                    },
+                ),
+            };
+            suffix.pop();
+            flow?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Call `found` with every solution, until it breaks.
+    fn search(&self, target: N, nums: &[N], found: &mut Found) -> ControlFlow<()> {
+        if nums.is_empty() {
+            return ControlFlow::Continue(());
+        }
+        match self.evaluation {
+            Evaluation::LeftToRight => {
+                let non_negative = nums.iter().all(|&n| n >= N::ZERO)
+                    && self.operators.iter().all(|o| o.keeps_non_negative());
+                self.backward(target, nums, non_negative, &mut Vec::new(), found)
+            }
+            Evaluation::Precedence => {
+                // operators only see their real operands once the whole expression is known
+                let mut ops = Vec::new();
+                self.each_assignment(nums.len() - 1, &mut ops, &mut |ops| {
+                    if self.evaluate(nums, ops) == Some(target) {
+                        found(ops)?;
+                    }
+                    ControlFlow::Continue(())
+                })
+            }
+        }
+    }
+
+    fn each_assignment(&self, gaps: usize, ops: &mut Vec<usize>, f: &mut Found) -> ControlFlow<()> {
+        if ops.len() == gaps {
+            return f(ops);
+        }
+        for i in 0..self.operators.len() {
+            ops.push(i);
+            let flow = self.each_assignment(gaps, ops, f);
+            ops.pop();
+            flow?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// The first solution found, if there is one.
+    pub fn solve(&self, target: N, nums: &[N]) -> Option<Solution> {
+        let mut first = None;
+        let _ = self.search(target, nums, &mut |ops| {
+            first = Some(ops.to_vec());
+            ControlFlow::Break(())
+        });
+        first
+    }
+
+    pub fn solvable(&self, target: N, nums: &[N]) -> bool {
+        self.solve(target, nums).is_some()
+    }
+
+    pub fn solutions(&self, target: N, nums: &[N]) -> Vec<Solution> {
+        let mut all = Vec::new();
+        let _ = self.search(target, nums, &mut |ops| {
+            all.push(ops.to_vec());
+            ControlFlow::Continue(())
+        });
+        all
+    }
+}
@@ -0,0 +1,9 @@
+//! Helpers shared between the days of 2024, used as
+//!
+//! ```toml
+//! [dependencies]
+//! advent-of-code-common = { path = "../common" }
+//! ```
+
+pub mod sections;
+pub mod testing;
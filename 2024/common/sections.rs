@@ -0,0 +1,130 @@
+//! Splitting puzzle input into blank-line separated sections, keeping track of line numbers so
+//! that parse errors can point at the offending line.
+//!
+//! Windows line endings, trailing whitespace, whitespace-only separator lines, and any number of
+//! blank lines before, between or after the sections are all accepted.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line number in the input.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A run of non-blank lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section<'a> {
+    /// `(line number, trimmed line)` pairs.
+    pub lines: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Section<'a> {
+    /// Parse every line with `f`, attaching the line number to the first error.
+    pub fn parse<T>(&self, f: impl Fn(&'a str) -> Result<T, String>) -> Result<Vec<T>, ParseError> {
+        self.lines
+            .iter()
+            .map(|&(line, text)| f(text).map_err(|message| ParseError::new(line, message)))
+            .collect()
+    }
+}
+
+pub fn sections(input: &str) -> Vec<Section<'_>> {
+    let mut sections = Vec::new();
+    let mut current = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            if !current.is_empty() {
+                sections.push(Section {
+                    lines: std::mem::take(&mut current),
+                });
+            }
+        } else {
+            current.push((i + 1, line));
+        }
+    }
+    if !current.is_empty() {
+        sections.push(Section { lines: current });
+    }
+    sections
+}
+
+/// Exactly `N` sections, or an error naming the sections that were expected.
+pub fn split<'a, const N: usize>(
+    input: &'a str,
+    names: [&str; N],
+) -> Result<[Section<'a>; N], ParseError> {
+    let sections = sections(input);
+    if sections.len() > N {
+        return Err(ParseError::new(
+            sections[N].lines[0].0,
+            format!("unexpected section after the {}", names[N - 1]),
+        ));
+    }
+    let found = sections.len();
+    sections.try_into().map_err(|_| {
+        ParseError::new(
+            input.lines().count() + 1,
+            format!("missing the {}", names[found]),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections() {
+        let input = "\r\n1|2\r\n3|4 \r\n\r\n  \n\n5,6,7\n8\n\n";
+        let sections = sections(input);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].lines, vec![(2, "1|2"), (3, "3|4")]);
+        assert_eq!(sections[1].lines, vec![(7, "5,6,7"), (8, "8")]);
+    }
+
+    #[test]
+    fn test_split() {
+        let [a, b] = split("a\n\nb", ["rules", "updates"]).unwrap();
+        assert_eq!(a.lines, vec![(1, "a")]);
+        assert_eq!(b.lines, vec![(3, "b")]);
+
+        assert_eq!(
+            split("a\nb\n", ["rules", "updates"]),
+            Err(ParseError::new(3, "missing the updates"))
+        );
+        assert_eq!(
+            split("a\n\nb\n\nc", ["rules", "updates"]),
+            Err(ParseError::new(5, "unexpected section after the updates"))
+        );
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let [numbers] = split("1\n2\nx\n4", ["numbers"]).unwrap();
+        let parsed = numbers.parse(|s| s.parse::<i32>().map_err(|e| e.to_string()));
+        assert_eq!(
+            parsed,
+            Err(ParseError::new(3, "invalid digit found in string"))
+        );
+    }
+}
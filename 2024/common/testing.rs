@@ -0,0 +1,61 @@
+//! Helpers for the days' tests and benchmarks, used as
+//!
+//! ```toml
+//! [dev-dependencies]
+//! advent-of-code-common = { path = "../common" }
+//! ```
+
+use std::fmt::Display;
+use std::time::Instant;
+
+/// Minimal xorshift generator, so that random tests are reproducible without extra crates.
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// A generator starting from `seed`, which must not be zero: zero is its own successor.
+    pub fn new(seed: u64) -> Self {
+        assert_ne!(seed, 0, "xorshift seed must not be zero");
+        XorShift(seed)
+    }
+
+    /// A number below `n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next().unwrap() % n
+    }
+}
+
+/// An endless stream of numbers.
+impl Iterator for XorShift {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        Some(self.0)
+    }
+}
+
+/// Run `f`, printing how long it took and what it returned under `label`, and pass its result on.
+pub fn time<T: Display>(label: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    println!("{:<24} {:>10.2?} {}", label, start.elapsed(), result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xorshift() {
+        let mut rng = XorShift::new(1);
+        assert_eq!(rng.next(), Some(1082269761));
+        assert!((0..1000).all(|_| rng.below(7) < 7));
+        // reproducible from the same seed
+        let (a, b) = (XorShift::new(0x2024), XorShift::new(0x2024));
+        assert!(a.take(100).eq(b.take(100)));
+    }
+}